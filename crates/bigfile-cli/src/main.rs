@@ -0,0 +1,232 @@
+use bigfile::{BigFile, BigFileOptions, ExtractOptions, error::BigFileError};
+use clap::{Args, Parser, Subcommand};
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+#[derive(Parser)]
+#[command(version, about = "Lists and extracts bigfiles")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Prints every file in the bigfile with its size
+    List {
+        #[command(flatten)]
+        bigfile: BigFileArgs,
+    },
+    /// Extracts every file in the bigfile
+    Extract {
+        #[command(flatten)]
+        bigfile: BigFileArgs,
+        /// Directory to extract into
+        #[arg(short, long)]
+        out: PathBuf,
+        /// Keep going when a file fails to extract, exiting with code 3 if any did
+        #[arg(long)]
+        lossy: bool,
+        /// Print the files that would be written instead of writing them
+        #[arg(long, conflicts_with = "lossy")]
+        dry_run: bool,
+        /// Skip files that were already extracted with the right size, to pick up
+        /// an extraction that was interrupted
+        #[arg(long, conflicts_with = "dry_run")]
+        resume: bool,
+    },
+    /// Reads every file without writing anything, exiting with code 3 if any can't be read
+    Verify {
+        #[command(flatten)]
+        bigfile: BigFileArgs,
+    },
+    /// Prints the hashes of the bfdb entries that no bfn path leads to, in hex
+    Orphans {
+        #[command(flatten)]
+        bigfile: BigFileArgs,
+        /// File with a candidate path on each line, printed next to the hash it matches
+        #[arg(long)]
+        wordlist: Option<PathBuf>,
+    },
+    /// Extracts a single file
+    Get {
+        #[command(flatten)]
+        bigfile: BigFileArgs,
+        /// Path of the file inside the bigfile, e.g. `textures/logo.dds`
+        path: PathBuf,
+        /// File to write to, the file is written to stdout when omitted
+        #[arg(short, long)]
+        out: Option<PathBuf>,
+    },
+}
+
+#[derive(Args)]
+struct BigFileArgs {
+    /// The bigfile.bfn file
+    bfn: PathBuf,
+    /// The bigfile.bfdb file, next to the bfn by default
+    #[arg(long)]
+    bfdb: Option<PathBuf>,
+    /// The bigfile.bfdata file, next to the bfn by default
+    #[arg(long)]
+    bfdata: Option<PathBuf>,
+}
+
+impl BigFileArgs {
+    fn open(self) -> bigfile::Result<BigFile> {
+        if self.bfdb.is_none() && self.bfdata.is_none() {
+            return BigFile::open(self.bfn);
+        }
+
+        let bfdb = self.bfdb.unwrap_or_else(|| sibling(&self.bfn, "bfdb"));
+        let bfdata = self.bfdata.unwrap_or_else(|| sibling(&self.bfn, "bfdata"));
+
+        BigFileOptions::new().open(self.bfn, bfdb, bfdata)
+    }
+}
+
+fn sibling(bfn_path: &Path, ext: &str) -> PathBuf {
+    bfn_path.with_extension(ext)
+}
+
+// Exit code for a lossy extraction that skipped some files or a verification that found
+// broken ones, 2 is taken by usage errors
+const PARTIAL_EXIT_CODE: u8 = 3;
+
+fn run(cli: Cli) -> bigfile::Result<ExitCode> {
+    match cli.command {
+        Command::List { bigfile } => {
+            let bigfile = bigfile.open()?;
+
+            let mut entries: Vec<_> = bigfile.entries().iter().collect();
+            entries.sort_by(|a, b| a.0.cmp(b.0));
+
+            let mut stdout = io::stdout().lock();
+            for (path, entry) in entries {
+                writeln!(stdout, "{}\t{}", entry.size(), path.display())?;
+            }
+        }
+        Command::Extract {
+            bigfile,
+            out,
+            dry_run: true,
+            ..
+        } => {
+            let bigfile = bigfile.open()?;
+            let plan = bigfile.extract_plan(out)?;
+
+            let mut stdout = io::stdout().lock();
+            for (path, size) in &plan {
+                writeln!(stdout, "{size}\t{}", path.display())?;
+            }
+
+            let total: u64 = plan.iter().map(|(_, size)| size).sum();
+            writeln!(stdout, "{} files, {total} bytes", plan.len())?;
+        }
+        Command::Extract {
+            bigfile,
+            out,
+            lossy: false,
+            resume,
+            ..
+        } => {
+            let bigfile = bigfile.open()?;
+            let options = ExtractOptions::new().output(out).resume(resume);
+            let report = bigfile.extract_with(&options)?;
+
+            if resume {
+                println!("{} files were already extracted", report.complete);
+            }
+        }
+        Command::Extract {
+            bigfile,
+            out,
+            lossy: true,
+            resume,
+            ..
+        } => {
+            let bigfile = bigfile.open()?;
+            let total = bigfile.len();
+            let options = ExtractOptions::new().output(out).lossy(true).resume(resume);
+            let report = bigfile.extract_with(&options)?;
+            let extracted = report.written.len() + report.complete;
+
+            println!("Extracted {extracted} of {total} files");
+            if extracted < total {
+                return Ok(ExitCode::from(PARTIAL_EXIT_CODE));
+            }
+        }
+        Command::Verify { bigfile } => {
+            let bigfile = bigfile.open()?;
+            let report = bigfile.verify_all()?;
+
+            for (path, err) in &report.failed {
+                eprintln!("{}: {err}", path.display());
+            }
+
+            println!("Verified {} of {} files", report.verified, bigfile.len());
+            if !report.failed.is_empty() {
+                return Ok(ExitCode::from(PARTIAL_EXIT_CODE));
+            }
+        }
+        Command::Orphans { bigfile, wordlist } => {
+            let bigfile = bigfile.open()?;
+            let orphans = bigfile.orphan_hashes();
+
+            let resolved = match wordlist {
+                Some(wordlist) => {
+                    let words = fs::read_to_string(&wordlist).map_err(|err| BigFileError::Io {
+                        file: Some(wordlist),
+                        offset: None,
+                        err,
+                    })?;
+                    let candidates = words.lines().map(|line| line.trim().to_string());
+                    bigfile.resolve_hashes(&orphans, candidates)
+                }
+                None => HashMap::new(),
+            };
+
+            let mut stdout = io::stdout().lock();
+            for hash in orphans {
+                match resolved.get(&hash) {
+                    Some(path) => writeln!(stdout, "{hash:016X}\t{path}")?,
+                    None => writeln!(stdout, "{hash:016X}")?,
+                }
+            }
+        }
+        Command::Get { bigfile, path, out } => {
+            let bigfile = bigfile.open()?;
+
+            match out {
+                Some(out) => {
+                    let file = File::create(&out).map_err(|err| BigFileError::Io {
+                        file: Some(out),
+                        offset: None,
+                        err,
+                    })?;
+                    bigfile.extract_entry_to(&path, BufWriter::new(file))?;
+                }
+                None => {
+                    bigfile.extract_entry_to(&path, io::stdout().lock())?;
+                }
+            }
+        }
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+fn main() -> ExitCode {
+    match run(Cli::parse()) {
+        Ok(code) => code,
+        Err(err) => {
+            eprintln!("error: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
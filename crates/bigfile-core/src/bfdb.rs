@@ -0,0 +1,45 @@
+use alloc::{format, vec::Vec};
+
+use crate::Source;
+
+// size, offset and hash, all u64
+pub(crate) const ENTRY_LEN: u64 = 24;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Entry {
+    pub offset: u64,
+    pub size: u64,
+}
+
+/// Reads the hash and entry of every record in a bfdb, in file order. `progress` is called
+/// with the number of records read so far and the total after each one.
+pub fn read_bfdb<S: Source>(
+    source: &mut S,
+    progress: &mut impl FnMut(u64, u64),
+) -> Result<Vec<(u64, Entry)>, S::Error> {
+    let len = source.read_u32()?;
+    log::debug!("bfdb has {len} entries");
+
+    // Guards against reserving gigabytes when given something that isn't a bfdb
+    let needed = len as u64 * ENTRY_LEN;
+    let remaining = source.remaining()?;
+    if needed > remaining {
+        return Err(source.invalid_data(format!(
+            "entry count {len} needs {needed} bytes, but only {remaining} bytes remain. \
+            Is this a bfdb file?"
+        )));
+    }
+
+    let mut records = Vec::with_capacity(len as _);
+
+    for i in 0..len {
+        let size = source.read_u64()?;
+        let offset = source.read_u64()?;
+        let hash = source.read_u64()?;
+
+        records.push((hash, Entry { offset, size }));
+        progress(i as u64 + 1, len as _);
+    }
+
+    Ok(records)
+}
@@ -0,0 +1,154 @@
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use crate::Source;
+
+/// A directory in a bfn, with its files and subdirectories in file order.
+pub struct BfnDir {
+    pub name: String,
+    pub files: Vec<String>,
+    pub dirs: Vec<BfnDir>,
+}
+
+impl BfnDir {
+    pub fn new(name: String) -> Self {
+        BfnDir {
+            name,
+            files: Vec::new(),
+            dirs: Vec::new(),
+        }
+    }
+
+    /// Returns the subdirectory with this name, adding it if there isn't one.
+    pub fn dir_mut(&mut self, name: &str) -> &mut BfnDir {
+        let index = match self.dirs.iter().position(|d| d.name == name) {
+            Some(i) => i,
+            None => {
+                self.dirs.push(BfnDir::new(name.to_string()));
+                self.dirs.len() - 1
+            }
+        };
+
+        &mut self.dirs[index]
+    }
+
+    /// Writes the directory little-endian, see `write_with_endianness`.
+    #[cfg(feature = "std")]
+    pub fn write(&self, writer: &mut impl std::io::Write) -> std::io::Result<()> {
+        self.write_with_endianness(writer, crate::Endianness::Little)
+    }
+
+    #[cfg(feature = "std")]
+    pub fn write_with_endianness(
+        &self,
+        writer: &mut impl std::io::Write,
+        order: crate::Endianness,
+    ) -> std::io::Result<()> {
+        let write_string = |writer: &mut dyn std::io::Write, string: &str| {
+            writer.write_all(&order.u32_bytes(string.len() as u32))?;
+            writer.write_all(string.as_bytes())
+        };
+
+        write_string(writer, &self.name)?;
+
+        writer.write_all(&order.u32_bytes(self.files.len() as u32))?;
+        for file in &self.files {
+            write_string(writer, file)?;
+        }
+
+        writer.write_all(&order.u32_bytes(self.dirs.len() as u32))?;
+        for dir in &self.dirs {
+            dir.write_with_endianness(writer, order)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Told about every directory and file as a bfn is read, in file order.
+/// A directory's files come before its subdirectories.
+pub trait BfnVisitor {
+    fn enter_dir(&mut self, _name: &str) {}
+
+    fn file(&mut self, _name: &str) {}
+
+    fn leave_dir(&mut self) {}
+}
+
+impl BfnVisitor for () {}
+
+/// Collects the full path of every file in a bfn, e.g. `./textures/logo.dds`.
+#[derive(Default)]
+pub struct Paths {
+    pub paths: Vec<String>,
+    dirs: Vec<String>,
+}
+
+impl BfnVisitor for Paths {
+    fn enter_dir(&mut self, name: &str) {
+        let path = join(self.dirs.last(), name);
+        self.dirs.push(path);
+    }
+
+    fn file(&mut self, name: &str) {
+        let path = join(self.dirs.last(), name);
+        self.paths.push(path);
+    }
+
+    fn leave_dir(&mut self) {
+        self.dirs.pop();
+    }
+}
+
+fn join(parent: Option<&String>, name: &str) -> String {
+    match parent {
+        Some(parent) if !parent.is_empty() => format!("{parent}/{name}"),
+        _ => name.to_string(),
+    }
+}
+
+/// Reads the directory tree of a bfn, telling `visitor` about each part of it as it's read.
+/// Fails if directories are nested deeper than `Source::max_depth`.
+pub fn read_bfn<S: Source>(
+    source: &mut S,
+    visitor: &mut impl BfnVisitor,
+) -> Result<BfnDir, S::Error> {
+    fn read_dir<S: Source>(
+        source: &mut S,
+        visitor: &mut impl BfnVisitor,
+        depth: usize,
+    ) -> Result<BfnDir, S::Error> {
+        let max_depth = source.max_depth();
+        if depth > max_depth {
+            return Err(source.invalid_data(format!(
+                "directories are nested more than {max_depth} levels deep"
+            )));
+        }
+
+        let name_len = source.read_u32()?;
+        let mut dir = BfnDir::new(source.read_string(name_len as _)?);
+        visitor.enter_dir(&dir.name);
+
+        let file_count = source.read_u32()?;
+        for _ in 0..file_count {
+            let len = source.read_u32()?;
+            let file_name = source.read_string(len as _)?;
+            visitor.file(&file_name);
+            dir.files.push(file_name);
+        }
+
+        let subdir_count = source.read_u32()?;
+        for _ in 0..subdir_count {
+            let subdir = read_dir(source, visitor, depth + 1)?;
+            dir.dirs.push(subdir);
+        }
+
+        visitor.leave_dir();
+        Ok(dir)
+    }
+
+    read_dir(source, visitor, 0)
+}
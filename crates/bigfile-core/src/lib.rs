@@ -0,0 +1,124 @@
+//! The bfn and bfdb formats on their own, without a filesystem or `std::io`. Parsing only
+//! needs a `Source` to read numbers and strings from, like `SliceSource` over a byte slice.
+//! The `bigfile` crate opens and extracts whole bigfiles on top of this.
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+pub mod bfdb;
+pub mod bfn;
+mod source;
+
+pub use crate::source::{
+    DEFAULT_MAX_DEPTH, MAX_STRING_LEN, ParseError, ParseErrorKind, SliceSource, Source,
+};
+
+pub fn fnv1a(string: &str) -> u64 {
+    let mut hash: u64 = 0xCBF29CE484222325;
+    for char in string.chars() {
+        hash ^= char as u64;
+        hash = hash.wrapping_mul(0x100000001B3);
+    }
+    hash
+}
+
+// How many characters at the start of a bfn path are left out of its hash. KarmaZoo's bfns
+// have a single-character root directory, so `./textures/logo.dds` is hashed as `textures/logo.dds`.
+// Bigfiles with a longer root name (or none) need a different amount.
+pub const DEFAULT_ROOT_STRIP: usize = 2;
+
+/// The hash the bfdb stores for a bfn path like `./textures/logo.dds`,
+/// leaving out the first `root_strip` characters.
+pub fn entry_hash(path: &str, root_strip: usize) -> u64 {
+    // The path passed to the hashing function should be lowercase,
+    // should replace all backslashes with normal slashes,
+    // and should not include the root directory
+    let path = path.replace('\\', "/").to_lowercase();
+    // Paths that are too short to have the root in them hash as empty instead of panicking
+    let stripped = match path.char_indices().nth(root_strip) {
+        Some((i, _)) => &path[i..],
+        None => "",
+    };
+    fnv1a(stripped)
+}
+
+/// One of the three files a bigfile is made of.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Part {
+    Bfn,
+    Bfdb,
+    Bfdata,
+}
+
+// Enough of the start of a file for `sniff` to see a bfn's root directory name and file count
+pub const SNIFF_LEN: usize = MAX_STRING_LEN + 8;
+
+/// Guesses which part of a little-endian bigfile a file is from `head`, its first
+/// `SNIFF_LEN` bytes or all of it if it's shorter, and `len`, its full length.
+/// Anything that doesn't look like a bfn or a bfdb is taken to be a bfdata.
+pub fn sniff(head: &[u8], len: u64) -> Part {
+    let u32_at = |at: usize| {
+        let bytes = head.get(at..at.checked_add(4)?)?;
+        Some(u32::from_le_bytes(bytes.try_into().ok()?))
+    };
+    let Some(first) = u32_at(0) else {
+        return Part::Bfdata;
+    };
+
+    // A bfdb is its entry count followed by exactly that many fixed-size records.
+    // That's checked first, as the start of its first record can pass for a bfn's name.
+    if 4 + first as u64 * bfdb::ENTRY_LEN == len {
+        return Part::Bfdb;
+    }
+
+    // A bfn starts with the name of its root directory and how many files are in it
+    let name_len = first as usize;
+    if name_len <= MAX_STRING_LEN
+        && let Some(name) = head.get(4..4 + name_len)
+        && core::str::from_utf8(name).is_ok_and(|name| !name.contains(char::is_control))
+        && u32_at(4 + name_len).is_some_and(|files| files as u64 * 4 <= len)
+    {
+        return Part::Bfn;
+    }
+
+    Part::Bfdata
+}
+
+/// The byte order of the numbers in the bfn and bfdb. PC builds are little-endian,
+/// console builds big-endian. Combined files always have a little-endian header.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Endianness {
+    #[default]
+    Little,
+    Big,
+}
+
+impl Endianness {
+    pub fn u32_bytes(self, value: u32) -> [u8; 4] {
+        match self {
+            Endianness::Little => value.to_le_bytes(),
+            Endianness::Big => value.to_be_bytes(),
+        }
+    }
+
+    pub fn u64_bytes(self, value: u64) -> [u8; 8] {
+        match self {
+            Endianness::Little => value.to_le_bytes(),
+            Endianness::Big => value.to_be_bytes(),
+        }
+    }
+
+    pub fn u32_from_bytes(self, bytes: [u8; 4]) -> u32 {
+        match self {
+            Endianness::Little => u32::from_le_bytes(bytes),
+            Endianness::Big => u32::from_be_bytes(bytes),
+        }
+    }
+
+    pub fn u64_from_bytes(self, bytes: [u8; 8]) -> u64 {
+        match self {
+            Endianness::Little => u64::from_le_bytes(bytes),
+            Endianness::Big => u64::from_be_bytes(bytes),
+        }
+    }
+}
@@ -0,0 +1,165 @@
+use alloc::{
+    format,
+    string::{String, ToString},
+};
+use core::fmt;
+
+use crate::Endianness;
+
+// File and directory names are never anywhere near this long
+pub const MAX_STRING_LEN: usize = 4096;
+
+// How deeply directories can be nested in a bfn by default. Real bfns are a handful of
+// levels deep, and the parser recurses once per level, so a crafted bfn can't overflow the stack.
+pub const DEFAULT_MAX_DEPTH: usize = 256;
+
+/// What the parsers read from. `bigfile` reads from `std::io` streams through this,
+/// `SliceSource` reads from a byte slice.
+pub trait Source {
+    type Error;
+
+    fn read_u32(&mut self) -> Result<u32, Self::Error>;
+
+    fn read_u64(&mut self) -> Result<u64, Self::Error>;
+
+    fn read_string(&mut self, len: usize) -> Result<String, Self::Error>;
+
+    // Number of bytes left to read
+    fn remaining(&mut self) -> Result<u64, Self::Error>;
+
+    // An error for data that doesn't make sense at the current position
+    fn invalid_data(&mut self, message: String) -> Self::Error;
+
+    // How deeply bfn directories can be nested before the bfn is rejected
+    fn max_depth(&self) -> usize {
+        DEFAULT_MAX_DEPTH
+    }
+}
+
+/// Reads a bfn or bfdb that's already in memory.
+pub struct SliceSource<'a> {
+    data: &'a [u8],
+    pos: usize,
+    endianness: Endianness,
+    max_depth: usize,
+}
+
+impl<'a> SliceSource<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        SliceSource {
+            data,
+            pos: 0,
+            endianness: Endianness::Little,
+            max_depth: DEFAULT_MAX_DEPTH,
+        }
+    }
+
+    pub fn endianness(mut self, endianness: Endianness) -> Self {
+        self.endianness = endianness;
+        self
+    }
+
+    /// How deeply bfn directories can be nested, `DEFAULT_MAX_DEPTH` by default.
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    fn read_bytes(&mut self, len: usize) -> Result<&'a [u8], ParseError> {
+        let Some(bytes) = self.data.get(self.pos..).and_then(|rest| rest.get(..len)) else {
+            return Err(self.error(ParseErrorKind::UnexpectedEof));
+        };
+
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn read_array<const N: usize>(&mut self) -> Result<[u8; N], ParseError> {
+        let mut bytes = [0; N];
+        bytes.copy_from_slice(self.read_bytes(N)?);
+        Ok(bytes)
+    }
+
+    fn error(&self, kind: ParseErrorKind) -> ParseError {
+        ParseError {
+            offset: self.pos,
+            kind,
+        }
+    }
+}
+
+impl Source for SliceSource<'_> {
+    type Error = ParseError;
+
+    fn read_u32(&mut self) -> Result<u32, ParseError> {
+        let bytes = self.read_array()?;
+        Ok(self.endianness.u32_from_bytes(bytes))
+    }
+
+    fn read_u64(&mut self) -> Result<u64, ParseError> {
+        let bytes = self.read_array()?;
+        Ok(self.endianness.u64_from_bytes(bytes))
+    }
+
+    fn read_string(&mut self, len: usize) -> Result<String, ParseError> {
+        if len > MAX_STRING_LEN {
+            return Err(self.invalid_data(format!(
+                "string length {len} is over the limit of {MAX_STRING_LEN} bytes"
+            )));
+        }
+
+        let offset = self.pos;
+        let bytes = self.read_bytes(len)?;
+        match core::str::from_utf8(bytes) {
+            Ok(string) => Ok(string.to_string()),
+            Err(_) => Err(ParseError {
+                offset,
+                kind: ParseErrorKind::InvalidUtf8,
+            }),
+        }
+    }
+
+    fn remaining(&mut self) -> Result<u64, ParseError> {
+        Ok(self.data.len().saturating_sub(self.pos) as u64)
+    }
+
+    fn invalid_data(&mut self, message: String) -> ParseError {
+        self.error(ParseErrorKind::InvalidData(message))
+    }
+
+    fn max_depth(&self) -> usize {
+        self.max_depth
+    }
+}
+
+/// Why `SliceSource` couldn't be parsed, at `offset` bytes into the slice.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseError {
+    pub offset: usize,
+    pub kind: ParseErrorKind,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParseErrorKind {
+    UnexpectedEof,
+    InvalidUtf8,
+    InvalidData(String),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "at offset {}: ", self.offset)?;
+
+        match &self.kind {
+            ParseErrorKind::UnexpectedEof => write!(f, "unexpected end of data"),
+            ParseErrorKind::InvalidUtf8 => write!(f, "read string was not UTF-8"),
+            ParseErrorKind::InvalidData(message) => write!(f, "{message}"),
+        }
+    }
+}
+
+impl core::error::Error for ParseError {}
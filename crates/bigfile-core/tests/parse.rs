@@ -0,0 +1,124 @@
+use bigfile_core::{
+    DEFAULT_ROOT_STRIP, Endianness, ParseError, ParseErrorKind, Part, SliceSource,
+    bfdb::{Entry, read_bfdb},
+    bfn::{Paths, read_bfn},
+    entry_hash, sniff,
+};
+
+fn string(out: &mut Vec<u8>, order: Endianness, s: &str) {
+    out.extend(order.u32_bytes(s.len() as u32));
+    out.extend(s.as_bytes());
+}
+
+// `./a.txt` and `./dir/b.txt`
+fn bfn(order: Endianness) -> Vec<u8> {
+    let mut out = Vec::new();
+    string(&mut out, order, ".");
+    out.extend(order.u32_bytes(1));
+    string(&mut out, order, "a.txt");
+    out.extend(order.u32_bytes(1));
+
+    string(&mut out, order, "dir");
+    out.extend(order.u32_bytes(1));
+    string(&mut out, order, "b.txt");
+    out.extend(order.u32_bytes(0));
+    out
+}
+
+#[test]
+fn bfn_paths() {
+    let data = bfn(Endianness::Little);
+    let mut paths = Paths::default();
+    let root = read_bfn(&mut SliceSource::new(&data), &mut paths).unwrap();
+
+    assert_eq!(paths.paths, ["./a.txt", "./dir/b.txt"]);
+    assert_eq!(root.files, ["a.txt"]);
+    assert_eq!(root.dirs[0].files, ["b.txt"]);
+
+    let mut written = Vec::new();
+    root.write(&mut written).unwrap();
+    assert_eq!(written, data);
+}
+
+#[test]
+fn big_endian() {
+    let data = bfn(Endianness::Big);
+    let mut source = SliceSource::new(&data).endianness(Endianness::Big);
+    let mut paths = Paths::default();
+    read_bfn(&mut source, &mut paths).unwrap();
+
+    assert_eq!(paths.paths, ["./a.txt", "./dir/b.txt"]);
+}
+
+#[test]
+fn bfdb_records() {
+    let hash = entry_hash("./Dir\\B.txt", DEFAULT_ROOT_STRIP);
+    assert_eq!(hash, entry_hash("./dir/b.txt", DEFAULT_ROOT_STRIP));
+
+    let mut data = 1u32.to_le_bytes().to_vec();
+    data.extend(5u64.to_le_bytes());
+    data.extend(16u64.to_le_bytes());
+    data.extend(hash.to_le_bytes());
+
+    let mut calls = Vec::new();
+    let records = read_bfdb(&mut SliceSource::new(&data), &mut |read, total| {
+        calls.push((read, total))
+    })
+    .unwrap();
+
+    assert_eq!(
+        records,
+        [(
+            hash,
+            Entry {
+                offset: 16,
+                size: 5
+            }
+        )]
+    );
+    assert_eq!(calls, [(1, 1)]);
+}
+
+#[test]
+fn errors_have_offsets() {
+    let data = bfn(Endianness::Little);
+    let Err(err) = read_bfn(&mut SliceSource::new(&data[..20]), &mut ()) else {
+        panic!("a cut off bfn was read");
+    };
+    assert_eq!(
+        err,
+        ParseError {
+            offset: 18,
+            kind: ParseErrorKind::UnexpectedEof
+        }
+    );
+
+    // Says there are a million entries, with none after it
+    let data = 1_000_000u32.to_le_bytes();
+    let err = read_bfdb(&mut SliceSource::new(&data), &mut |_, _| {}).unwrap_err();
+    assert!(matches!(err.kind, ParseErrorKind::InvalidData(_)));
+    assert_eq!(err.offset, 4);
+}
+
+#[test]
+fn sniff_parts() {
+    let bfn = bfn(Endianness::Little);
+    assert_eq!(sniff(&bfn, bfn.len() as u64), Part::Bfn);
+
+    let mut bfdb = 2u32.to_le_bytes().to_vec();
+    bfdb.extend([0; 48]);
+    assert_eq!(sniff(&bfdb, bfdb.len() as u64), Part::Bfdb);
+    assert_eq!(sniff(&0u32.to_le_bytes(), 4), Part::Bfdb);
+
+    // The count reads as the length of a name, and the first hash as that name
+    let mut bfdb = 2u32.to_le_bytes().to_vec();
+    bfdb.extend(b"ab");
+    bfdb.extend([0; 46]);
+    assert_eq!(sniff(&bfdb, bfdb.len() as u64), Part::Bfdb);
+
+    let dds = b"DDS |\0\0\0\x07\x10\0\0";
+    assert_eq!(sniff(dds, 4096), Part::Bfdata);
+    assert_eq!(sniff(b"", 0), Part::Bfdata);
+    // A name length that runs past the end
+    assert_eq!(sniff(&[200, 0, 0, 0, b'a'], 5), Part::Bfdata);
+}
@@ -1,22 +1,43 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-use bigfile::{BigFile, DataSource, error::BigFileError};
+mod preview;
+mod thumbnails;
+
+use bigfile::{
+    BigFile, BigFileOptions, ExtractOptions, Part, Stats, extension, format_size, normalize_path,
+    sanitize_path,
+};
 use eframe::egui::{
-    self, Align, Button, Context, IconData, Id, ImageSource, InnerResponse, Key, KeyboardShortcut,
-    Layout, Modal, ModalResponse, Modifiers, TextWrapMode, Ui, Widget,
+    self, Align, Align2, Button, Context, IconData, Id, ImageSource, InnerResponse, Key,
+    KeyboardShortcut, Layout, Modal, ModalResponse, Modifiers, TextWrapMode, TextureHandle,
+    ThemePreference, Ui, Widget, load::SizedTexture,
 };
 use rfd::FileDialog;
 use std::{
-    collections::BTreeMap,
+    cmp::Reverse,
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    ffi::OsString,
     fs,
-    io::{Cursor, Read},
+    io::{self, BufWriter},
     path::{Path, PathBuf},
-    rc::Rc,
-    sync::Arc,
+    process::Command,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
 };
+use thumbnails::{IMAGE_EXTENSIONS, THUMBNAIL_SIZE, Thumbnails};
 
 const APP_VERSION: &str = env!("CARGO_PKG_VERSION");
 
+const RECENT_KEY: &str = "recent";
+const SORT_KEY: &str = "sort";
+const LOAD_POLICY_KEY: &str = "load_policy";
+const THEME_KEY: &str = "theme";
+const MAX_RECENT: usize = 10;
+
 // `egui::Context::format_shortcut` displays ⌘ as Cmd,
 // which I don't like, so I decided to make my own function.
 // Yes, that's the only reason why I ditched `format_shortcut`.
@@ -40,6 +61,7 @@ impl Shortcut {
 
 const OPEN_SHORTCUT: Shortcut = Shortcut::new(Modifiers::COMMAND, Key::O, "⌘ O", "Ctrl + O");
 const CLOSE_SHORTCUT: Shortcut = Shortcut::new(Modifiers::COMMAND, Key::W, "⌘ W", "Ctrl + W");
+const RELOAD_SHORTCUT: Shortcut = Shortcut::new(Modifiers::COMMAND, Key::R, "⌘ R", "Ctrl + R");
 const EXTRACT_ALL_SHORTCUT: Shortcut = Shortcut::new(
     Modifiers::COMMAND.plus(Modifiers::SHIFT),
     Key::E,
@@ -48,12 +70,29 @@ const EXTRACT_ALL_SHORTCUT: Shortcut = Shortcut::new(
 );
 const EXTRACT_SELECTED_SHORTCUT: Shortcut =
     Shortcut::new(Modifiers::COMMAND, Key::E, "⌘ E", "Ctrl + E");
+const SELECT_ALL_SHORTCUT: Shortcut = Shortcut::new(Modifiers::COMMAND, Key::A, "⌘ A", "Ctrl + A");
+const DESELECT_ALL_SHORTCUT: Shortcut = Shortcut::new(
+    Modifiers::COMMAND.plus(Modifiers::SHIFT),
+    Key::A,
+    "⌘ Shift A",
+    "Ctrl + Shift + A",
+);
+const GRID_SHORTCUT: Shortcut = Shortcut::new(Modifiers::COMMAND, Key::G, "⌘ G", "Ctrl + G");
 
 #[derive(Default)]
 struct File {
     name: String,
     id: u64,
+    // Index of the archive in `App::archives` this file belongs to
+    archive: usize,
     path: PathBuf,
+    size: u64,
+    // Where the file's data starts in its bfdata
+    offset: u64,
+    // Lowercase path for case-insensitive searching
+    search_path: String,
+    // Lowercase, empty for files without one
+    extension: String,
 }
 
 impl PartialEq for File {
@@ -63,30 +102,244 @@ impl PartialEq for File {
 }
 
 impl File {
-    fn new(name: String, id: u64, path: PathBuf) -> Self {
-        Self { name, id, path }
+    fn new(name: String, id: u64, archive: usize, path: PathBuf, size: u64, offset: u64) -> Self {
+        let search_path = path.to_string_lossy().to_lowercase();
+        Self {
+            name,
+            id,
+            archive,
+            extension: extension(&path),
+            path,
+            size,
+            offset,
+            search_path,
+        }
     }
 }
 
+#[derive(Clone, Copy, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+enum SortBy {
+    #[default]
+    Name,
+    // Largest first, directories included
+    Size,
+    // In bfdata order, directories stay sorted by name
+    Offset,
+}
+
+impl SortBy {
+    fn label(self) -> &'static str {
+        match self {
+            SortBy::Name => "Name",
+            SortBy::Size => "Size",
+            SortBy::Offset => "Offset",
+        }
+    }
+}
+
+// What the central panel shows
+#[derive(Clone, Copy, Default, PartialEq)]
+enum View {
+    #[default]
+    Preview,
+    // Thumbnails of the images next to the selected file
+    Grid,
+}
+
+// Decides whether a bfdata is read into memory or from disk as needed, by its size
+#[derive(Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+struct LoadPolicy {
+    // Bfdatas below this many MB are read into memory without asking
+    memory_below_mb: u64,
+    // Bfdatas above this many MB are read from disk without asking
+    disk_above_mb: u64,
+    // Asks about the bfdatas in between, otherwise they're read from disk
+    ask: bool,
+}
+
+impl Default for LoadPolicy {
+    fn default() -> Self {
+        LoadPolicy {
+            memory_below_mb: 64,
+            disk_above_mb: 1024,
+            ask: true,
+        }
+    }
+}
+
+impl LoadPolicy {
+    // Whether to read a bfdata of `size` bytes into memory, or `None` to ask.
+    // Bfdatas of an unknown size are treated like the ones in between.
+    fn in_memory(&self, size: Option<u64>) -> Option<bool> {
+        match size.map(|size| size / 1024 / 1024) {
+            Some(mb) if mb < self.memory_below_mb => Some(true),
+            Some(mb) if mb > self.disk_above_mb => Some(false),
+            _ if self.ask => None,
+            _ => Some(false),
+        }
+    }
+}
+
+// What the tree is narrowed down to
+#[derive(Clone, Copy)]
+struct Filter<'a> {
+    // Should already be lowercase
+    text: &'a str,
+    // Shows every extension when empty
+    extensions: &'a BTreeSet<String>,
+}
+
+impl Filter<'_> {
+    fn is_empty(&self) -> bool {
+        self.text.is_empty() && self.extensions.is_empty()
+    }
+
+    fn matches(&self, file: &File) -> bool {
+        file.search_path.contains(self.text)
+            && (self.extensions.is_empty() || self.extensions.contains(&file.extension))
+    }
+}
+
+// A line of the tree as it was shown, with the directory it's in
+enum Row {
+    Dir { id: Id, parent: Option<Id> },
+    File { file: Arc<File>, parent: Option<Id> },
+}
+
+impl Row {
+    fn focus(&self) -> Focus {
+        match self {
+            Row::Dir { id, .. } => Focus::Dir(*id),
+            Row::File { file, .. } => Focus::File(Arc::clone(file)),
+        }
+    }
+
+    fn parent(&self) -> Option<Id> {
+        match self {
+            Row::Dir { parent, .. } | Row::File { parent, .. } => *parent,
+        }
+    }
+
+    fn file(&self) -> Option<&Arc<File>> {
+        match self {
+            Row::Dir { .. } => None,
+            Row::File { file, .. } => Some(file),
+        }
+    }
+}
+
+// The row that the arrow keys move from
+#[derive(PartialEq)]
+enum Focus {
+    Dir(Id),
+    File(Arc<File>),
+}
+
+struct TreeContext<'a> {
+    selected: &'a mut Vec<Arc<File>>,
+    anchor: &'a mut Option<Arc<File>>,
+    focus: &'a mut Option<Focus>,
+    // Rows in the order they were shown in the previous frame, for shift-click ranges
+    // and keyboard navigation
+    previous: &'a [Row],
+    shown: Vec<Row>,
+    // The directory being shown, if it isn't the root
+    parent: Option<Id>,
+    // Set after the focus was moved with the keyboard
+    scroll_to_focus: bool,
+    filter: Filter<'a>,
+    sort: SortBy,
+    // For hashing paths the way their own archive does
+    archives: &'a [Archive],
+}
+
+impl TreeContext<'_> {
+    fn click(&mut self, file: &Arc<File>, modifiers: Modifiers) {
+        *self.focus = Some(Focus::File(Arc::clone(file)));
+
+        let previous: Vec<_> = self.previous.iter().filter_map(Row::file).collect();
+        if modifiers.shift
+            && let Some(anchor) = self.anchor
+            && let Some(from) = previous.iter().position(|&f| f == anchor)
+            && let Some(to) = previous.iter().position(|&f| f == file)
+        {
+            if !modifiers.command {
+                self.selected.clear();
+            }
+
+            let range = &previous[from.min(to)..=from.max(to)];
+            for &file in range {
+                if !self.selected.contains(file) {
+                    self.selected.push(Arc::clone(file));
+                }
+            }
+
+            // The anchor stays put, so that the range can be adjusted with another shift-click
+            return;
+        }
+
+        if modifiers.command_only() {
+            self.selected.push(Arc::clone(file));
+        } else {
+            self.selected.clear();
+            self.selected.push(Arc::clone(file));
+        }
+
+        *self.anchor = Some(Arc::clone(file));
+    }
+}
+
+// What `Dir::insert` needs to know about a file besides its path
+struct Leaf<'a> {
+    archive: usize,
+    size: u64,
+    offset: u64,
+    suffix: Option<&'a str>,
+}
+
 #[derive(Default)]
 struct Dir {
-    files: Vec<Rc<File>>,
+    files: Vec<Arc<File>>,
     dirs: BTreeMap<String, Dir>,
+    size: u64,
 }
 
 impl Dir {
-    fn from_paths(paths: &Vec<&PathBuf>) -> Dir {
+    fn from_archives(archives: &[Archive]) -> Dir {
         let mut root = Dir::default();
         let mut id = 0;
 
-        for path in paths {
-            root.insert(&path, &mut id, Path::new(""));
+        let mut counts: HashMap<&PathBuf, usize> = HashMap::new();
+        for archive in archives {
+            for path in archive.bigfile.paths() {
+                *counts.entry(path).or_default() += 1;
+            }
+        }
+
+        for (i, archive) in archives.iter().enumerate() {
+            for (path, entry) in archive.bigfile.entries() {
+                // Files with the same path in different archives are shown side by side,
+                // so they need something to tell them apart
+                let suffix = if counts[path] > 1 {
+                    Some(archive.name.as_str())
+                } else {
+                    None
+                };
+
+                let file = Leaf {
+                    archive: i,
+                    size: entry.size(),
+                    offset: entry.offset(),
+                    suffix,
+                };
+                root.insert(path, &file, &mut id, Path::new(""));
+            }
         }
 
         root
     }
 
-    fn insert(&mut self, path: &Path, id: &mut u64, prefix: &Path) {
+    fn insert(&mut self, path: &Path, leaf: &Leaf, id: &mut u64, prefix: &Path) {
         let parts: Vec<String> = path
             .iter()
             .map(|p| p.to_string_lossy().to_string())
@@ -94,14 +347,27 @@ impl Dir {
 
         if let Some((first, rest)) = parts.split_first() {
             let prefix = prefix.join(first);
+            self.size += leaf.size;
 
             if rest.is_empty() {
-                self.files
-                    .push(Rc::new(File::new(first.clone(), *id, prefix)));
+                let name = match leaf.suffix {
+                    Some(suffix) => format!("{first} ({suffix})"),
+                    None => first.clone(),
+                };
+
+                self.files.push(Arc::new(File::new(
+                    name,
+                    *id,
+                    leaf.archive,
+                    prefix,
+                    leaf.size,
+                    leaf.offset,
+                )));
                 *id += 1;
             } else {
                 self.dirs.entry(first.clone()).or_default().insert(
                     Path::new(&rest.join("/")),
+                    leaf,
                     id,
                     &prefix,
                 );
@@ -109,277 +375,949 @@ impl Dir {
         }
     }
 
-    fn show(&mut self, ui: &mut egui::Ui, selected: &mut Vec<Rc<File>>, root: bool) {
-        for (dir, subdir) in &mut self.dirs {
+    fn matches(&self, filter: Filter) -> bool {
+        self.files.iter().any(|f| filter.matches(f))
+            || self.dirs.values().any(|d| d.matches(filter))
+    }
+
+    fn dir_at(&self, path: &Path) -> Option<&Dir> {
+        path.iter().try_fold(self, |dir, name| {
+            dir.dirs.get(name.to_string_lossy().as_ref())
+        })
+    }
+
+    fn collect_files(&self, filter: Filter, out: &mut Vec<Arc<File>>) {
+        for dir in self.dirs.values() {
+            dir.collect_files(filter, out);
+        }
+
+        out.extend(
+            self.files
+                .iter()
+                .filter(|f| filter.matches(f))
+                .map(Arc::clone),
+        );
+    }
+
+    fn show(&mut self, ui: &mut egui::Ui, cx: &mut TreeContext, root: bool) {
+        let filtering = !cx.filter.is_empty();
+
+        let mut dirs: Vec<_> = self.dirs.iter_mut().collect();
+        if cx.sort == SortBy::Size {
+            // Stable, so directories of the same size stay sorted by name
+            dirs.sort_by_key(|(_, dir)| Reverse(dir.size));
+        }
+
+        for (dir, subdir) in dirs {
+            if filtering && !subdir.matches(cx.filter) {
+                continue;
+            }
+
             if root {
-                subdir.show(ui, selected, false);
+                subdir.show(ui, cx, false);
             } else {
-                egui::CollapsingHeader::new(dir).show(ui, |ui| subdir.show(ui, selected, false));
+                // While searching, every directory left has a match in it, so keep them all open
+                let open = if filtering { Some(true) } else { None };
+
+                // The same id that the header below ends up with
+                let id = ui.make_persistent_id(Id::new(dir));
+                let focused = *cx.focus == Some(Focus::Dir(id));
+                cx.shown.push(Row::Dir {
+                    id,
+                    parent: cx.parent,
+                });
+
+                let parent = cx.parent.replace(id);
+                let header =
+                    egui::CollapsingHeader::new(format!("{dir}  ({})", format_size(subdir.size)))
+                        .id_salt(dir)
+                        .open(open)
+                        .show_background(focused)
+                        .show(ui, |ui| subdir.show(ui, cx, false));
+                cx.parent = parent;
+
+                if header.header_response.clicked() {
+                    *cx.focus = Some(Focus::Dir(id));
+                }
+                if focused && cx.scroll_to_focus {
+                    header.header_response.scroll_to_me(None);
+                }
+
+                header.header_response.context_menu(|ui| {
+                    if ui.button("Select all in folder").clicked() {
+                        cx.selected.clear();
+                        subdir.collect_files(cx.filter, cx.selected);
+                        ui.close();
+                    }
+                });
             }
         }
 
-        self.files.sort_by(|a, b| a.name.cmp(&b.name));
+        match cx.sort {
+            SortBy::Name => self.files.sort_by(|a, b| a.name.cmp(&b.name)),
+            SortBy::Size => self
+                .files
+                .sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.name.cmp(&b.name))),
+            SortBy::Offset => self.files.sort_by_key(|f| (f.archive, f.offset)),
+        }
 
         for file in &self.files {
-            let selectable = Button::selectable(selected.contains(&file), &file.name)
-                .wrap_mode(TextWrapMode::Extend)
-                .ui(ui);
+            if filtering && !cx.filter.matches(file) {
+                continue;
+            }
+
+            cx.shown.push(Row::File {
+                file: Arc::clone(file),
+                parent: cx.parent,
+            });
+
+            let selectable = ui
+                .horizontal(|ui| {
+                    let selectable = Button::selectable(cx.selected.contains(file), &file.name)
+                        .wrap_mode(TextWrapMode::Extend)
+                        .ui(ui);
+
+                    ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+                        ui.weak(format_size(file.size));
+                    });
+
+                    selectable
+                })
+                .inner;
 
             if selectable.clicked() {
-                if ui.input(|i| i.modifiers).command_only() {
-                    selected.push(Rc::clone(&file));
-                } else {
-                    selected.clear();
-                    selected.push(Rc::clone(&file));
-                }
+                cx.click(file, ui.input(|i| i.modifiers));
             }
+            if cx.scroll_to_focus && matches!(cx.focus, Some(Focus::File(f)) if f == file) {
+                selectable.scroll_to_me(None);
+            }
+
+            selectable.context_menu(|ui| {
+                if ui.button("Copy path").clicked() {
+                    ui.ctx().copy_text(normalize_path(&file.path));
+                    ui.close();
+                }
+                if ui.button("Copy hash").clicked() {
+                    let hash = cx.archives[file.archive].bigfile.hash_of(&file.path);
+                    ui.ctx().copy_text(format!("{hash:016X}"));
+                    ui.close();
+                }
+            });
         }
     }
 }
 
-fn open_bigfile(path: &PathBuf) -> bigfile::Result<fs::File> {
-    fs::File::open(path).map_err(|err| BigFileError::Io {
-        file: Some(path.clone()),
-        offset: None,
-        err,
-    })
+fn show_stats(ui: &mut Ui, stats: &Stats) {
+    ui.label("Files");
+    ui.label(stats.entries.to_string());
+    ui.end_row();
+
+    ui.label("Total size");
+    ui.label(format!(
+        "{} ({} bytes)",
+        format_size(stats.total_size),
+        stats.total_size
+    ));
+    ui.end_row();
+
+    ui.label("Mean size");
+    ui.label(format_size(stats.mean_size as u64));
+    ui.end_row();
+
+    ui.label("Median size");
+    ui.label(format_size(stats.median_size));
+    ui.end_row();
+
+    if let Some((path, size)) = &stats.largest {
+        ui.label("Largest");
+        ui.label(format!("{} ({})", path.display(), format_size(*size)))
+            .on_hover_text(path.display().to_string());
+        ui.end_row();
+    }
+
+    for (ext, count) in stats.extensions_by_count() {
+        match ext {
+            "" => ui.label("No extension"),
+            ext => ui.label(format!(".{ext}")),
+        };
+        ui.label(count.to_string());
+        ui.end_row();
+    }
 }
 
-fn read_bigfile(path: &PathBuf, buf: &mut Vec<u8>) -> bigfile::Result<usize> {
-    let mut file = open_bigfile(path)?;
-    file.read_to_end(buf).map_err(|err| BigFileError::Io {
-        file: Some(path.clone()),
-        offset: None,
-        err,
-    })
+fn show_selection_details(ui: &mut Ui, files: &[Arc<File>]) {
+    let total: u64 = files.iter().map(|f| f.size).sum();
+
+    ui.label("Files");
+    ui.label(files.len().to_string());
+    ui.end_row();
+
+    ui.label("Total size");
+    ui.label(format!("{} ({total} bytes)", format_size(total)));
+    ui.end_row();
+
+    if let Some(largest) = files.iter().max_by_key(|f| f.size) {
+        ui.label("Largest");
+        ui.label(format!("{} ({})", largest.name, format_size(largest.size)));
+        ui.end_row();
+    }
+}
+
+// egui caches images by URI, so the URI includes the preview generation. Otherwise a file
+// with the same path in another archive, or with new data, would show the old image.
+fn preview_uri(file: &File, generation: u64) -> String {
+    format!("bytes://{generation}/{}", file.path.to_string_lossy())
+}
+
+fn scrub_uri(file: &File, generation: u64, offset: usize) -> String {
+    format!("{}@{offset}", preview_uri(file, generation))
+}
+
+#[derive(Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+struct RecentBigfile {
+    bfn: PathBuf,
+    bfdb: PathBuf,
+    bfdata: PathBuf,
+}
+
+impl RecentBigfile {
+    fn exists(&self) -> bool {
+        self.bfn.is_file() && self.bfdb.is_file() && self.bfdata.is_file()
+    }
+}
+
+#[derive(Clone)]
+struct Archive {
+    bigfile: Arc<BigFile>,
+    name: String,
+    bfn_path: PathBuf,
+    bfdb_path: PathBuf,
+    bfdata_path: PathBuf,
+}
+
+struct Extraction {
+    done: Arc<AtomicUsize>,
+    total: usize,
+    cancel: Arc<AtomicBool>,
+    // Whether the extraction was cancelled
+    handle: JoinHandle<bigfile::Result<bool>>,
+    output: PathBuf,
 }
 
 #[derive(Default)]
 struct App {
-    bigfile: Option<BigFile>,
+    archives: Vec<Archive>,
     tree: Dir,
-    selected: Vec<Rc<File>>,
+    // Builds the tree and finds the extensions in it, off the UI thread as it can take
+    // a while for huge bigfiles
+    tree_build: Option<JoinHandle<(Dir, BTreeSet<String>)>>,
+    // Archive indices and paths of the files to select again once the tree is rebuilt
+    reselect: HashSet<(usize, PathBuf)>,
+    selected: Vec<Arc<File>>,
+    anchor: Option<Arc<File>>,
+    focus: Option<Focus>,
+    scroll_to_focus: bool,
+    shown: Vec<Row>,
+    filter: String,
+    // Extensions the tree is narrowed down to, see `Filter`
+    extensions: BTreeSet<String>,
+    // Every extension in the open bigfiles, to pick from
+    all_extensions: BTreeSet<String>,
+    sort: SortBy,
+    load_policy: LoadPolicy,
+    // Follows the system's theme by default, where the platform reports it
+    theme: ThemePreference,
     bfn_path: Option<PathBuf>,
     bfdb_path: Option<PathBuf>,
     bfdata_path: Option<PathBuf>,
     bigfile_modal: Option<String>,
-    error_modal: Option<String>,
-    extract_modal: Option<String>,
-    preview_image: (PathBuf, Arc<[u8]>),
+    // Shown in a corner without blocking anything, until they're dismissed
+    errors: Vec<String>,
+    // The text and the file or folder the modal offers to show in the file manager
+    extract_modal: Option<(String, PathBuf)>,
+    // Archive names and their stats, while the properties window is open
+    properties: Option<Vec<(String, Stats)>>,
+    extraction: Option<Extraction>,
+    preview_image: Option<(Arc<File>, Arc<[u8]>)>,
+    // Decoded DDS texture for the file in `preview_image`
+    preview_texture: Option<Result<TextureHandle, String>>,
+    // Goes up every time a file is read for the preview, see `preview_uri`
+    preview_generation: u64,
+    // Offset into the previewed file to look for embedded images at, when scrubbing
+    scrub_offset: Option<usize>,
+    // The bytes from the scrub offset on, by `scrub_uri`, copied once rather than every frame
+    scrub_image: Option<(String, Arc<[u8]>)>,
+    recent: Vec<RecentBigfile>,
+    view: View,
+    // The directory the grid shows and the images in it, sorted by name
+    grid: Option<(PathBuf, Vec<Arc<File>>)>,
+    thumbnails: Thumbnails,
 }
 
 impl App {
+    fn new(cc: &eframe::CreationContext) -> Self {
+        let recent = cc
+            .storage
+            .and_then(|storage| eframe::get_value(storage, RECENT_KEY))
+            .unwrap_or_default();
+        let sort = cc
+            .storage
+            .and_then(|storage| eframe::get_value(storage, SORT_KEY))
+            .unwrap_or_default();
+        let load_policy = cc
+            .storage
+            .and_then(|storage| eframe::get_value(storage, LOAD_POLICY_KEY))
+            .unwrap_or_default();
+        let theme = cc
+            .storage
+            .and_then(|storage| eframe::get_value(storage, THEME_KEY))
+            .unwrap_or_default();
+        cc.egui_ctx.set_theme(theme);
+
+        App {
+            recent,
+            sort,
+            load_policy,
+            theme,
+            ..Default::default()
+        }
+    }
+
+    fn add_recent(&mut self, bfn: PathBuf, bfdb: PathBuf, bfdata: PathBuf) {
+        let recent = RecentBigfile { bfn, bfdb, bfdata };
+
+        self.recent.retain(|r| *r != recent);
+        self.recent.insert(0, recent);
+        self.recent.truncate(MAX_RECENT);
+    }
+
     fn error(&mut self, text: String) {
         eprintln!("err: {text}");
-        self.error_modal = Some(text);
+        self.errors.push(text);
     }
 
+    // `in_memory` reads the whole bfdata into memory up front
     fn load_bigfile(
         &mut self,
         bfn_path: PathBuf,
         bfdb_path: PathBuf,
         bfdata_path: PathBuf,
+        in_memory: bool,
     ) -> bigfile::error::Result<()> {
-        let bigfile = BigFile::from_paths(bfn_path, bfdb_path, DataSource::File(bfdata_path))?;
+        let bigfile = BigFileOptions::new().in_memory(in_memory).open(
+            bfn_path.clone(),
+            bfdb_path.clone(),
+            bfdata_path.clone(),
+        )?;
 
-        self.tree = Dir::from_paths(&bigfile.entries().keys().collect());
-        self.bigfile = Some(bigfile);
+        self.add_archive(bigfile, bfn_path, bfdb_path, bfdata_path);
 
         Ok(())
     }
 
-    fn load_bigfile_buf(
+    fn add_archive(
         &mut self,
+        bigfile: BigFile,
         bfn_path: PathBuf,
         bfdb_path: PathBuf,
         bfdata_path: PathBuf,
-    ) -> bigfile::error::Result<()> {
-        let mut buf = vec![];
-        read_bigfile(&bfdata_path, &mut buf)?;
+    ) {
+        self.add_recent(bfn_path.clone(), bfdb_path.clone(), bfdata_path.clone());
+
+        let archive = Archive {
+            bigfile: Arc::new(bigfile),
+            name: bfn_path
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string(),
+            bfn_path,
+            bfdb_path,
+            bfdata_path,
+        };
+
+        // Reopening an archive that's already open replaces it
+        match self
+            .archives
+            .iter()
+            .position(|a| a.bfn_path == archive.bfn_path)
+        {
+            Some(i) => self.archives[i] = archive,
+            None => self.archives.push(archive),
+        }
 
-        let cur = Cursor::new(buf);
-        let bfdata = DataSource::Buffer(cur);
-        let bigfile = BigFile::from_paths(bfn_path, bfdb_path, bfdata)?;
+        self.rebuild_tree();
+    }
 
-        self.tree = Dir::from_paths(&bigfile.entries().keys().collect());
-        self.bigfile = Some(bigfile);
+    fn rebuild_tree(&mut self) {
+        if self.properties.is_some() {
+            self.properties = Some(self.archive_stats());
+        }
 
-        Ok(())
+        // File ids and archive indices change when the tree is rebuilt
+        self.selected.clear();
+        self.anchor = None;
+        self.focus = None;
+        self.shown.clear();
+        self.preview_image = None;
+        self.preview_texture = None;
+        self.grid = None;
+        self.thumbnails.clear();
+        self.tree = Dir::default();
+
+        let archives = self.archives.clone();
+        self.tree_build = Some(thread::spawn(move || {
+            let tree = Dir::from_archives(&archives);
+            let extensions = archives
+                .iter()
+                .flat_map(|a| a.bigfile.stats().by_extension.into_keys())
+                .collect();
+
+            (tree, extensions)
+        }));
+    }
+
+    fn poll_tree_build(&mut self, ctx: &Context) {
+        let Some(handle) = &self.tree_build else {
+            return;
+        };
+
+        if !handle.is_finished() {
+            // Nothing else triggers a repaint while the tree is being built
+            ctx.request_repaint_after(Duration::from_millis(50));
+            return;
+        }
+
+        match self.tree_build.take().unwrap().join() {
+            Ok((tree, extensions)) => {
+                self.tree = tree;
+                self.all_extensions = extensions;
+                self.extensions
+                    .retain(|ext| self.all_extensions.contains(ext));
+
+                if !self.reselect.is_empty() {
+                    let everything = Filter {
+                        text: "",
+                        extensions: &BTreeSet::new(),
+                    };
+                    let mut files = Vec::new();
+                    self.tree.collect_files(everything, &mut files);
+                    self.selected = files
+                        .into_iter()
+                        .filter(|f| self.reselect.contains(&(f.archive, f.path.clone())))
+                        .collect();
+                    self.reselect.clear();
+                }
+            }
+            Err(_) => self.error("Building the file tree panicked".to_string()),
+        }
     }
 
     fn show_tree(&mut self, ui: &mut egui::Ui) {
-        self.tree.show(ui, &mut self.selected, true);
+        let filter = self.filter.to_lowercase();
+        let mut cx = TreeContext {
+            selected: &mut self.selected,
+            anchor: &mut self.anchor,
+            focus: &mut self.focus,
+            previous: &self.shown,
+            shown: Vec::new(),
+            parent: None,
+            scroll_to_focus: self.scroll_to_focus,
+            filter: Filter {
+                text: &filter,
+                extensions: &self.extensions,
+            },
+            sort: self.sort,
+            archives: &self.archives,
+        };
+
+        self.tree.show(ui, &mut cx, true);
+        self.shown = cx.shown;
+        self.scroll_to_focus = false;
+    }
+
+    // Moves the focus through the rows shown in the last frame, opening and closing directories
+    fn navigate(&mut self, ctx: &Context, key: Key) {
+        let Some(last) = self.shown.len().checked_sub(1) else {
+            return;
+        };
+        let current = self
+            .focus
+            .as_ref()
+            .and_then(|focus| self.shown.iter().position(|row| row.focus() == *focus));
+
+        let Some(i) = current else {
+            // Nothing is focused yet, so start at the top
+            if matches!(key, Key::ArrowDown | Key::ArrowUp) {
+                self.focus_row(0);
+            }
+            return;
+        };
+
+        match (key, &self.shown[i]) {
+            (Key::ArrowDown, _) => self.focus_row((i + 1).min(last)),
+            (Key::ArrowUp, _) => self.focus_row(i.saturating_sub(1)),
+            (Key::ArrowLeft, &Row::Dir { id, .. }) if dir_is_open(ctx, id) => {
+                set_dir_open(ctx, id, false)
+            }
+            (Key::ArrowLeft, row) => {
+                if let Some(parent) = row.parent() {
+                    self.focus = Some(Focus::Dir(parent));
+                    self.scroll_to_focus = true;
+                }
+            }
+            (Key::ArrowRight, &Row::Dir { id, .. }) => {
+                if dir_is_open(ctx, id) {
+                    self.focus_row((i + 1).min(last));
+                } else {
+                    set_dir_open(ctx, id, true);
+                }
+            }
+            (Key::Enter, &Row::Dir { id, .. }) => set_dir_open(ctx, id, !dir_is_open(ctx, id)),
+            (Key::Enter, Row::File { .. }) => self.focus_row(i),
+            _ => {}
+        }
+    }
+
+    // Focuses a row, selecting it if it's a file, like a plain click would
+    fn focus_row(&mut self, i: usize) {
+        let row = &self.shown[i];
+        if let Some(file) = row.file() {
+            self.selected.clear();
+            self.selected.push(Arc::clone(file));
+            self.anchor = Some(Arc::clone(file));
+        }
+
+        self.focus = Some(row.focus());
+        self.scroll_to_focus = true;
+    }
+
+    // Selects every file that's currently shown in the tree
+    fn select_all(&mut self) {
+        let text = self.filter.to_lowercase();
+        let filter = Filter {
+            text: &text,
+            extensions: &self.extensions,
+        };
+
+        self.selected.clear();
+        self.tree.collect_files(filter, &mut self.selected);
+    }
+
+    // Reads every open bigfile again, keeping the selected files that are still there
+    fn reload_bigfiles(&mut self) {
+        let mut failed = Vec::new();
+        for archive in &mut self.archives {
+            // Extractions and the tree build hold on to the bigfile until they're done
+            let result = match Arc::get_mut(&mut archive.bigfile) {
+                Some(bigfile) => bigfile.reload().map_err(|e| e.to_string()),
+                None => Err("it's still in use".to_string()),
+            };
+
+            if let Err(e) = result {
+                failed.push(format!("{}: {e}", archive.name));
+            }
+        }
+
+        if !failed.is_empty() {
+            self.error(format!("Couldn't reload:\n{}", failed.join("\n")));
+        }
+
+        self.reselect = self
+            .selected
+            .iter()
+            .map(|f| (f.archive, f.path.clone()))
+            .collect();
+        self.rebuild_tree();
+    }
+
+    fn can_reload(&self) -> bool {
+        !self.archives.is_empty() && self.extraction.is_none() && self.tree_build.is_none()
     }
 
     fn unload_bigfile(&mut self) {
-        self.bigfile = None;
+        self.archives.clear();
         self.tree = Dir::default();
+        self.tree_build = None;
+        self.reselect.clear();
+        self.properties = None;
         self.selected.clear();
+        self.anchor = None;
+        self.focus = None;
+        self.shown.clear();
+        self.filter.clear();
+        self.extensions.clear();
+        self.all_extensions.clear();
+        self.preview_image = None;
+        self.preview_texture = None;
+        self.grid = None;
+        self.thumbnails.clear();
         self.bfn_path = None;
         self.bfdb_path = None;
         self.bfdata_path = None;
     }
 
     fn add_bigfile(&mut self) {
-        if let Some(bfn_path) = open_bigfile_dialog("bfn")
-            && let Some(bfdb_path) = auto_open_or_dialog(&bfn_path, "bfdb")
+        if let Some(bfn_path) = open_bigfile_dialog("bfn") {
+            self.open_bfn(bfn_path);
+        }
+    }
+
+    fn open_bfn(&mut self, bfn_path: PathBuf) {
+        if let Some(bfdb_path) = auto_open_or_dialog(&bfn_path, "bfdb")
             && let Some(bfdata_path) = auto_open_or_dialog(&bfn_path, "bfdata")
         {
-            let text = if let Ok(metadata) = fs::metadata(&bfdata_path) {
-                let mb = metadata.len() / 1024 / 1024;
-                format!(
-                    "{} is {mb} MB in size.\n\
-                    Do you want to load the entire file into memory?\n\
-                    Pressing \"No\" will read data from disk as needed.",
-                    &bfdata_path
-                        .file_name()
-                        .unwrap_or_default()
-                        .to_string_lossy()
-                        .to_string()
-                )
-            } else {
-                format!(
-                    "Do you want to load the entire file into memory?
-                    Pressing \"No\" will read data from disk as needed."
-                )
+            self.prompt_load(bfn_path, bfdb_path, bfdata_path);
+        }
+    }
+
+    // Opens files given on the command line or dropped on the window. Which part each one is
+    // is told from its contents, so they can be in any order and have any extension.
+    // Parts that are left out are found next to the bfn like with File -> Open.
+    fn open_files(&mut self, paths: Vec<PathBuf>) {
+        let (mut bfn, mut bfdb, mut bfdata) = (None, None, None);
+
+        for path in paths {
+            let part = match bigfile::sniff_file(&path) {
+                Ok(part) => part,
+                Err(e) => return self.error(e.to_string()),
+            };
+            let slot = match part {
+                Part::Bfn => &mut bfn,
+                Part::Bfdb => &mut bfdb,
+                Part::Bfdata => &mut bfdata,
             };
 
-            self.bfn_path = Some(bfn_path);
-            self.bfdb_path = Some(bfdb_path);
-            self.bfdata_path = Some(bfdata_path);
+            if let Some(other) = slot.replace(path) {
+                return self.error(format!(
+                    "{} and {} both look like a {}",
+                    file_name(&other),
+                    file_name(slot.as_ref().unwrap()),
+                    part_name(part)
+                ));
+            }
+        }
+
+        let Some(bfn_path) = bfn else {
+            return self.error("None of the files look like a bfn".to_string());
+        };
+
+        let bfdb_path = bfdb.or_else(|| auto_open_or_dialog(&bfn_path, "bfdb"));
+        let bfdata_path = bfdata.or_else(|| auto_open_or_dialog(&bfn_path, "bfdata"));
 
-            self.bigfile_modal = Some(text);
+        if let (Some(bfdb_path), Some(bfdata_path)) = (bfdb_path, bfdata_path) {
+            self.prompt_load(bfn_path, bfdb_path, bfdata_path);
         }
     }
 
+    // Loads the bigfile as `load_policy` says, or asks whether to load the bfdata into memory
+    // when it's in between, the answer is handled by `show_bigfile_modal`
+    fn prompt_load(&mut self, bfn_path: PathBuf, bfdb_path: PathBuf, bfdata_path: PathBuf) {
+        let size = fs::metadata(&bfdata_path).ok().map(|m| m.len());
+
+        if let Some(in_memory) = self.load_policy.in_memory(size) {
+            if let Err(e) = self.load_bigfile(bfn_path, bfdb_path, bfdata_path, in_memory) {
+                self.error(format!("{e:?}"));
+            }
+            return;
+        }
+
+        let text = if let Some(size) = size {
+            let mb = size / 1024 / 1024;
+            format!(
+                "{} is {mb} MB in size.\n\
+                Do you want to load the entire file into memory?\n\
+                Pressing \"No\" will read data from disk as needed.",
+                &bfdata_path
+                    .file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .to_string()
+            )
+        } else {
+            "Do you want to load the entire file into memory?\n\
+            Pressing \"No\" will read data from disk as needed."
+                .to_string()
+        };
+
+        self.bfn_path = Some(bfn_path);
+        self.bfdb_path = Some(bfdb_path);
+        self.bfdata_path = Some(bfdata_path);
+
+        self.bigfile_modal = Some(text);
+    }
+
     fn extract_all(&mut self) {
-        if let Some(path) = open_extract_dialog()
-            && let Some(bigfile) = &self.bigfile
+        if self.extraction.is_none()
+            && !self.archives.is_empty()
+            && let Some(path) = open_extract_dialog()
         {
-            if let Err(e) = bigfile.extract(path) {
-                self.error(format!("{e:?}"));
-            } else {
-                self.extract_modal = Some(format!(
-                    "Finished extracting {} files",
-                    bigfile.entries().len()
-                ));
+            let bigfiles: Vec<_> = self
+                .archives
+                .iter()
+                .map(|a| Arc::clone(&a.bigfile))
+                .collect();
+            let done = Arc::new(AtomicUsize::new(0));
+            let total = bigfiles.iter().map(|b| b.len()).sum();
+            let cancel = Arc::new(AtomicBool::new(false));
+
+            let counter = Arc::clone(&done);
+            let flag = Arc::clone(&cancel);
+            let output = path.clone();
+            let handle = thread::spawn(move || {
+                let mut previous = 0;
+                for bigfile in bigfiles {
+                    let counter = Arc::clone(&counter);
+                    let options = ExtractOptions::new()
+                        .output(path.clone())
+                        .progress(move |extracted, _| {
+                            counter.store(previous + extracted, Ordering::Relaxed)
+                        })
+                        .cancel(Arc::clone(&flag));
+
+                    if bigfile.extract_with(&options)?.cancelled {
+                        return Ok(true);
+                    }
+                    previous += bigfile.len();
+                }
+
+                Ok(false)
+            });
+
+            self.extraction = Some(Extraction {
+                done,
+                total,
+                cancel,
+                handle,
+                output,
+            });
+        }
+    }
+
+    fn show_extraction(&mut self, ctx: &Context) {
+        let Some(extraction) = &self.extraction else {
+            return;
+        };
+
+        if extraction.handle.is_finished() {
+            let extraction = self.extraction.take().unwrap();
+            match extraction.handle.join() {
+                Ok(Ok(false)) => {
+                    let text = format!("Finished extracting {} files", extraction.total);
+                    self.extract_modal = Some((text, extraction.output));
+                }
+                Ok(Ok(true)) => {
+                    let done = extraction.done.load(Ordering::Relaxed);
+                    let text = format!(
+                        "Cancelled after extracting {done} of {} files",
+                        extraction.total
+                    );
+                    self.extract_modal = Some((text, extraction.output));
+                }
+                Ok(Err(e)) => self.error(format!("{e:?}")),
+                Err(_) => self.error("The extraction thread panicked".to_string()),
             }
+            return;
         }
+
+        let done = extraction.done.load(Ordering::Relaxed);
+        let total = extraction.total;
+
+        Modal::new(Id::new("extraction")).show(ctx, |ui| {
+            ui.label("Extracting files…");
+            ui.add_space(8.0);
+            ui.add(
+                egui::ProgressBar::new(done as f32 / total.max(1) as f32)
+                    .desired_width(320.0)
+                    .text(format!("{done} / {total}")),
+            );
+            ui.add_space(8.0);
+
+            let cancelling = extraction.cancel.load(Ordering::Relaxed);
+            if ui
+                .add_enabled(!cancelling, egui::Button::new("Cancel"))
+                .clicked()
+            {
+                extraction.cancel.store(true, Ordering::Relaxed);
+            }
+        });
+
+        // Nothing else triggers a repaint while the extraction thread is working
+        ctx.request_repaint_after(Duration::from_millis(50));
     }
 
     fn common_prefix(&self) -> PathBuf {
-        let mut iters: Vec<_> = self
+        bigfile::common_prefix(self.selected.iter().map(|file| file.path.as_path()))
+    }
+
+    fn extract_selected(&mut self) {
+        let Some(export_path) = open_extract_dialog() else {
+            return;
+        };
+        let prefix = self.common_prefix();
+
+        let results = self
             .selected
             .iter()
-            .map(|p| p.path.parent().unwrap_or(Path::new("")).components())
+            .map(|file| self.extract_file(file, &prefix, &export_path))
             .collect();
 
-        let mut prefix = PathBuf::new();
+        self.report_extracted(results, export_path);
+    }
 
-        'outer: loop {
-            let mut next = None;
-            for comps in &mut iters {
-                match comps.next() {
-                    Some(c) => {
-                        if let Some(n) = next {
-                            if c != n {
-                                break 'outer;
-                            }
-                        } else {
-                            next = Some(c);
-                        }
-                    }
-                    None => break 'outer,
+    // Extracts the selected files straight into one folder, leaving out their directories.
+    // Files with the same name are numbered, see `ExtractOptions::flatten`. Files from more
+    // than one bigfile are put in a folder per bigfile, so that they're numbered apart.
+    fn extract_selected_flat(&mut self) {
+        let Some(export_path) = open_extract_dialog() else {
+            return;
+        };
+
+        let mut selected: Vec<HashSet<PathBuf>> = vec![HashSet::new(); self.archives.len()];
+        for file in &self.selected {
+            selected[file.archive].insert(file.path.clone());
+        }
+        let several = selected.iter().filter(|paths| !paths.is_empty()).count() > 1;
+
+        let mut results = Vec::new();
+        for (archive, paths) in self.archives.iter().zip(selected) {
+            if paths.is_empty() {
+                continue;
+            }
+
+            let output = if several {
+                export_path.join(&archive.name)
+            } else {
+                export_path.clone()
+            };
+            let options = ExtractOptions::new()
+                .output(output)
+                .flatten(true)
+                .lossy(true)
+                .filter(move |path| paths.contains(path));
+
+            match archive.bigfile.extract_with(&options) {
+                Ok(report) => {
+                    results.extend(report.written.into_iter().map(Ok));
+                    results.extend(
+                        report
+                            .failed
+                            .into_iter()
+                            .map(|(path, e)| Err(format!("{}: {e}", path.display()))),
+                    );
                 }
+                Err(e) => results.push(Err(e.to_string())),
             }
-            prefix.push(next.unwrap().as_os_str());
         }
 
-        prefix
+        self.report_extracted(results, export_path);
     }
 
-    fn extract_selected(&mut self) {
-        if let Some(export_path) = open_extract_dialog()
-            && let Some(bigfile) = &self.bigfile
-        {
-            let prefix = self.common_prefix();
-
-            for file in &self.selected {
-                match bigfile.get(&file.path) {
-                    Ok(v) => {
-                        let path =
-                            export_path.join(file.path.strip_prefix(&prefix).unwrap_or(&file.path));
-
-                        if let Err(e) = fs::create_dir_all(&path.parent().unwrap()) {
-                            // trying to replace it with a self.error() call results in
-                            // "cannot borrow *self as mutable" and i cba to figure out a way to fix it
-                            let text = format!(
-                                "Failed to extract file {}. {e:?}",
-                                path.canonicalize().unwrap_or(path).display()
-                            );
-
-                            eprintln!("err: {text}");
-                            self.error_modal = Some(text);
-
-                            continue;
-                        }
+    // Takes where each selected file was extracted to, or why it wasn't. A single file
+    // is revealed from the modal, several open the folder they were extracted into.
+    fn report_extracted(&mut self, results: Vec<Result<PathBuf, String>>, export_path: PathBuf) {
+        let mut written = Vec::new();
+        let mut failures = Vec::new();
+        for result in results {
+            match result {
+                Ok(path) => written.push(path),
+                Err(e) => failures.push(e),
+            }
+        }
 
-                        if let Err(e) = fs::write(&path, v) {
-                            // trying to replace it with a self.error() call results in
-                            // "cannot borrow *self as mutable" and i cba to figure out a way to fix it
-                            let text = format!(
-                                "Failed to extract file {}. {e:?}",
-                                path.canonicalize().unwrap_or(path).display()
-                            );
+        self.report_failures(failures);
 
-                            eprintln!("err: {text}");
-                            self.error_modal = Some(text);
-                        }
-                    }
-                    Err(e) => {
-                        // trying to replace it with a self.error() call results in
-                        // "cannot borrow *self as mutable" and i cba to figure out a way to fix it
-                        let text =
-                            format!("Failed to extract file {}. {e:?}", &file.path.display());
-
-                        eprintln!("err: {text}");
-                        self.error_modal = Some(text);
-                    }
-                };
-            }
+        self.extract_modal = match written.as_slice() {
+            [] => None,
+            [path] => Some((format!("Extracted {}", file_name(path)), path.clone())),
+            _ => Some((format!("Extracted {} files", written.len()), export_path)),
+        };
+    }
+
+    fn report_failures(&mut self, failures: Vec<String>) {
+        if !failures.is_empty() {
+            self.error(format!(
+                "{} of {} files failed to extract:\n{}",
+                failures.len(),
+                self.selected.len(),
+                failures.join("\n")
+            ));
         }
     }
 
-    fn show_extract_modal(&mut self, ctx: &Context, text: &String) -> ModalResponse<()> {
+    // Extracts a selected file to its path relative to `prefix` in `export_path`,
+    // returning where it ended up
+    fn extract_file(
+        &self,
+        file: &File,
+        prefix: &Path,
+        export_path: &Path,
+    ) -> Result<PathBuf, String> {
+        let relative = file.path.strip_prefix(prefix).unwrap_or(&file.path);
+        let relative = sanitize_path(relative).map_err(|e| e.to_string())?;
+        let path = export_path.join(relative);
+        self.extract_file_to(file, &path)?;
+        Ok(path)
+    }
+
+    fn extract_file_to(&self, file: &File, path: &Path) -> Result<(), String> {
+        let failed = |e: &dyn std::fmt::Display| format!("{}: {e}", path.display());
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| failed(&e))?;
+        }
+        let out = fs::File::create(path).map_err(|e| failed(&e))?;
+
+        self.archives[file.archive]
+            .bigfile
+            .extract_entry_to(&file.path, BufWriter::new(out))
+            .map_err(|e| failed(&e))?;
+
+        Ok(())
+    }
+
+    fn show_extract_modal(
+        &mut self,
+        ctx: &Context,
+        text: &String,
+        output: &Path,
+    ) -> ModalResponse<()> {
         show_modal(ctx, "extract".into(), text, |ui| {
-            if ui.button("OK").clicked() {
-                ui.close();
-                self.extract_modal = None;
-            }
+            ui.horizontal(|ui| {
+                if ui.button("OK").clicked() {
+                    ui.close();
+                    self.extract_modal = None;
+                }
+
+                let label = if output.is_dir() {
+                    "Open Output Folder"
+                } else {
+                    "Reveal"
+                };
+                if ui.button(label).clicked() {
+                    ui.close();
+                    self.extract_modal = None;
+
+                    if let Err(e) = reveal(output) {
+                        self.error(format!("Couldn't open {}: {e}", output.display()));
+                    }
+                }
+            });
         })
     }
 
     fn show_bigfile_modal(&mut self, ctx: &Context, text: &String) -> ModalResponse<()> {
-        show_modal(ctx, "bf".into(), &text, |ui| {
+        show_modal(ctx, "bf".into(), text, |ui| {
             ui.horizontal(|ui| {
                 let yes = ui.button("Yes");
                 let no = ui.button("No");
 
-                if no.clicked() {
+                if yes.clicked() || no.clicked() {
                     if let Err(e) = self.load_bigfile(
                         self.bfn_path.clone().unwrap_or_default(),
                         self.bfdb_path.clone().unwrap_or_default(),
                         self.bfdata_path.clone().unwrap_or_default(),
-                    ) {
-                        self.error(format!("{e:?}"));
-                    }
-                    ui.close();
-                    self.bigfile_modal = None;
-                } else if yes.clicked() {
-                    if let Err(e) = self.load_bigfile_buf(
-                        self.bfn_path.clone().unwrap_or_default(),
-                        self.bfdb_path.clone().unwrap_or_default(),
-                        self.bfdata_path.clone().unwrap_or_default(),
+                        yes.clicked(),
                     ) {
                         self.error(format!("{e:?}"));
                     }
@@ -390,31 +1328,98 @@ impl App {
         })
     }
 
-    fn show_error_modal(&mut self, ctx: &Context, err: &String) -> ModalResponse<()> {
-        Modal::new(Id::new("err")).show(ctx, |ui| {
-            ui.heading("ERROR!");
-            ui.label(err);
+    fn show_errors(&mut self, ctx: &Context) {
+        if self.errors.is_empty() {
+            return;
+        }
+
+        // Above the bottom panel
+        egui::Area::new(Id::new("errors"))
+            .anchor(Align2::RIGHT_BOTTOM, [-8.0, -32.0])
+            .order(egui::Order::Foreground)
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.set_max_width(420.0);
+
+                    if self.errors.len() > 1 {
+                        ui.horizontal(|ui| {
+                            ui.strong(format!("{} errors", self.errors.len()));
+                            if ui.small_button("Dismiss all").clicked() {
+                                self.errors.clear();
+                            }
+                        });
+                        ui.separator();
+                    }
 
-            ui.add_space(32.0);
+                    let mut dismissed = None;
+                    egui::ScrollArea::vertical()
+                        .max_height(240.0)
+                        .show(ui, |ui| {
+                            // Newest first
+                            for (i, err) in self.errors.iter().enumerate().rev() {
+                                ui.horizontal(|ui| {
+                                    if ui.small_button("×").clicked() {
+                                        dismissed = Some(i);
+                                    }
+                                    ui.add(egui::Label::new(err).wrap());
+                                });
+                            }
+                        });
 
-            if ui.button("OK").clicked() {
-                ui.close();
-                self.error_modal = None;
-            }
-        })
+                    if let Some(i) = dismissed {
+                        self.errors.remove(i);
+                    }
+                });
+            });
     }
 
     fn show_modals(&mut self, ctx: &Context) {
-        if let Some(text) = self.error_modal.clone() {
-            self.show_error_modal(ctx, &text);
-        }
+        self.show_extraction(ctx);
+
+        self.show_errors(ctx);
 
         if let Some(text) = self.bigfile_modal.clone() {
             self.show_bigfile_modal(ctx, &text);
         }
 
-        if let Some(text) = self.extract_modal.clone() {
-            self.show_extract_modal(ctx, &text);
+        if let Some((text, output)) = self.extract_modal.clone() {
+            self.show_extract_modal(ctx, &text, &output);
+        }
+
+        self.show_properties(ctx);
+    }
+
+    fn archive_stats(&self) -> Vec<(String, Stats)> {
+        self.archives
+            .iter()
+            .map(|a| (a.name.clone(), a.bigfile.stats()))
+            .collect()
+    }
+
+    fn show_properties(&mut self, ctx: &Context) {
+        let Some(properties) = &self.properties else {
+            return;
+        };
+
+        let mut open = true;
+        egui::Window::new("Properties")
+            .open(&mut open)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    for (i, (name, stats)) in properties.iter().enumerate() {
+                        ui.strong(name);
+                        egui::Grid::new(("properties", i))
+                            .num_columns(2)
+                            .striped(true)
+                            .show(ui, |ui| show_stats(ui, stats));
+                        ui.add_space(8.0);
+                    }
+                });
+            });
+
+        if !open {
+            self.properties = None;
         }
     }
 
@@ -425,6 +1430,7 @@ impl App {
                     ui.vertical(|ui| {
                         let open = Button::new("Open").shortcut_text(OPEN_SHORTCUT.text);
                         let close = Button::new("Close").shortcut_text(CLOSE_SHORTCUT.text);
+                        let reload = Button::new("Reload").shortcut_text(RELOAD_SHORTCUT.text);
                         let extract =
                             Button::new("Extract All").shortcut_text(EXTRACT_ALL_SHORTCUT.text);
 
@@ -432,119 +1438,666 @@ impl App {
                             self.add_bigfile();
                         }
 
-                        if ui.add_enabled(self.bigfile.is_some(), close).clicked() {
+                        ui.menu_button("Open Recent", |ui| {
+                            let mut chosen = None;
+
+                            for recent in &self.recent {
+                                let text = recent.bfn.to_string_lossy();
+                                if ui.add_enabled(recent.exists(), Button::new(text)).clicked() {
+                                    chosen = Some(recent.clone());
+                                }
+                            }
+
+                            if self.recent.is_empty() {
+                                ui.add_enabled(false, Button::new("No recent bigfiles"));
+                            } else {
+                                ui.separator();
+
+                                if ui.button("Remove Missing").clicked() {
+                                    self.recent.retain(RecentBigfile::exists);
+                                }
+
+                                if ui.button("Clear").clicked() {
+                                    self.recent.clear();
+                                }
+                            }
+
+                            if let Some(recent) = chosen {
+                                self.prompt_load(recent.bfn, recent.bfdb, recent.bfdata);
+                            }
+                        });
+
+                        if ui
+                            .add_enabled(!self.archives.is_empty(), Button::new("Properties"))
+                            .clicked()
+                        {
+                            self.properties = Some(self.archive_stats());
+                        }
+
+                        if ui.add_enabled(self.can_reload(), reload).clicked() {
+                            self.reload_bigfiles();
+                        }
+
+                        if ui.add_enabled(!self.archives.is_empty(), close).clicked() {
                             self.unload_bigfile();
                         }
 
-                        if ui.add_enabled(self.bigfile.is_some(), extract).clicked() {
+                        if ui.add_enabled(!self.archives.is_empty(), extract).clicked() {
                             self.extract_all();
                         }
+
+                        ui.separator();
+                        ui.menu_button("Load into Memory", |ui| {
+                            self.show_load_policy(ui);
+                        });
+                    })
+                });
+
+                ui.menu_button("View", |ui| {
+                    ui.vertical(|ui| {
+                        let themes = [
+                            (ThemePreference::System, "Follow System"),
+                            (ThemePreference::Dark, "Dark"),
+                            (ThemePreference::Light, "Light"),
+                        ];
+
+                        for (theme, label) in themes {
+                            if ui.radio_value(&mut self.theme, theme, label).changed() {
+                                ctx.set_theme(theme);
+                            }
+                        }
+
+                        ui.separator();
+
+                        let grid = Button::selectable(self.view == View::Grid, "Thumbnail Grid")
+                            .shortcut_text(GRID_SHORTCUT.text);
+                        if ui.add_enabled(!self.archives.is_empty(), grid).clicked() {
+                            self.toggle_grid();
+                        }
                     })
                 });
 
                 ui.menu_button("Selection", |ui| {
                     ui.vertical(|ui| {
+                        let select_all =
+                            Button::new("Select All").shortcut_text(SELECT_ALL_SHORTCUT.text);
+                        let deselect_all =
+                            Button::new("Deselect All").shortcut_text(DESELECT_ALL_SHORTCUT.text);
+
+                        if ui
+                            .add_enabled(!self.archives.is_empty(), select_all)
+                            .clicked()
+                        {
+                            self.select_all();
+                        }
+
+                        if ui
+                            .add_enabled(!self.selected.is_empty(), deselect_all)
+                            .clicked()
+                        {
+                            self.selected.clear();
+                        }
+
+                        ui.separator();
+
                         let btn = Button::new("Extract Selected")
                             .shortcut_text(EXTRACT_SELECTED_SHORTCUT.text);
                         if ui.add_enabled(!self.selected.is_empty(), btn).clicked() {
                             self.extract_selected();
                         }
+
+                        let btn = Button::new("Extract Selected to Flat Folder");
+                        if ui.add_enabled(!self.selected.is_empty(), btn).clicked() {
+                            self.extract_selected_flat();
+                        }
                     })
                 });
             });
         })
     }
 
+    fn show_load_policy(&mut self, ui: &mut Ui) {
+        let policy = &mut self.load_policy;
+
+        egui::Grid::new("load_policy")
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label("Always below");
+                ui.add(
+                    egui::DragValue::new(&mut policy.memory_below_mb)
+                        .range(0..=policy.disk_above_mb)
+                        .suffix(" MB"),
+                );
+                ui.end_row();
+
+                ui.label("Never above");
+                ui.add(
+                    egui::DragValue::new(&mut policy.disk_above_mb)
+                        .range(policy.memory_below_mb..=u64::MAX)
+                        .suffix(" MB"),
+                );
+                ui.end_row();
+            });
+
+        ui.checkbox(&mut policy.ask, "Ask in between");
+    }
+
     fn show_left_panel(&mut self, ctx: &Context) {
         egui::SidePanel::left("left_panel")
             .resizable(true)
             .width_range(80.0..=640.0)
             .show(ctx, |ui| {
+                ui.add_space(4.0);
+                ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+                    self.show_extension_menu(ui);
+                    egui::ComboBox::from_id_salt("sort")
+                        .selected_text(self.sort.label())
+                        .show_ui(ui, |ui| {
+                            for sort in [SortBy::Name, SortBy::Size, SortBy::Offset] {
+                                ui.selectable_value(&mut self.sort, sort, sort.label());
+                            }
+                        });
+                    egui::TextEdit::singleline(&mut self.filter)
+                        .hint_text("Search")
+                        .desired_width(f32::INFINITY)
+                        .show(ui);
+                });
+                ui.add_space(4.0);
+
+                if self.tree_build.is_some() {
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        ui.label("Loading tree…");
+                    });
+                    return;
+                }
+
                 egui::ScrollArea::vertical().show(ui, |ui| {
                     self.show_tree(ui);
                 });
             });
     }
 
+    fn show_extension_menu(&mut self, ui: &mut Ui) {
+        let text = match self.extensions.len() {
+            0 => "All types".to_string(),
+            1 => "1 type".to_string(),
+            n => format!("{n} types"),
+        };
+
+        ui.menu_button(text, |ui| {
+            if ui
+                .add_enabled(!self.extensions.is_empty(), Button::new("Show all"))
+                .clicked()
+            {
+                self.extensions.clear();
+            }
+            ui.separator();
+
+            egui::ScrollArea::vertical()
+                .max_height(320.0)
+                .show(ui, |ui| {
+                    for ext in &self.all_extensions {
+                        let mut shown = self.extensions.contains(ext);
+                        let label = if ext.is_empty() {
+                            "No extension".to_string()
+                        } else {
+                            format!(".{ext}")
+                        };
+
+                        if ui.checkbox(&mut shown, label).changed() {
+                            if shown {
+                                self.extensions.insert(ext.clone());
+                            } else {
+                                self.extensions.remove(ext);
+                            }
+                        }
+                    }
+                });
+        });
+    }
+
+    fn show_details_panel(&mut self, ctx: &Context) {
+        egui::SidePanel::right("details_panel")
+            .resizable(true)
+            .width_range(160.0..=480.0)
+            .show(ctx, |ui| {
+                ui.add_space(4.0);
+                egui::Grid::new("details")
+                    .num_columns(2)
+                    .striped(true)
+                    .show(ui, |ui| match self.selected.as_slice() {
+                        [file] => self.show_file_details(ui, file),
+                        files => show_selection_details(ui, files),
+                    });
+            });
+    }
+
+    fn show_file_details(&self, ui: &mut Ui, file: &File) {
+        let archive = &self.archives[file.archive];
+        let entry = archive
+            .bigfile
+            .find(&file.path)
+            .and_then(|path| archive.bigfile.entries().get(path));
+
+        ui.label("Path");
+        ui.label(file.path.to_string_lossy());
+        ui.end_row();
+
+        if self.archives.len() > 1 {
+            ui.label("Bigfile");
+            ui.label(&archive.name);
+            ui.end_row();
+        }
+
+        ui.label("Size");
+        ui.label(format!("{} ({} bytes)", format_size(file.size), file.size));
+        ui.end_row();
+
+        if let Some(entry) = entry {
+            ui.label("Offset");
+            ui.label(format!("{} (0x{:X})", entry.offset(), entry.offset()));
+            ui.end_row();
+        }
+
+        ui.label("Hash");
+        ui.label(format!("{:016X}", archive.bigfile.hash_of(&file.path)));
+        ui.end_row();
+    }
+
     fn show_bottom_panel(&mut self, ctx: &Context) {
         egui::TopBottomPanel::bottom("bottom_panel").show(ctx, |ui| {
             ui.horizontal(|ui| {
-                if self.bigfile.is_some() {
+                if let [archive] = self.archives.as_slice() {
                     ui.label(format!(
                         "{} • {} • {}",
-                        self.bfn_path
-                            .clone()
-                            .unwrap_or_default()
+                        archive
+                            .bfn_path
                             .file_name()
                             .unwrap_or_default()
-                            .to_string_lossy()
-                            .to_string(),
-                        self.bfdb_path
-                            .clone()
-                            .unwrap_or_default()
+                            .to_string_lossy(),
+                        archive
+                            .bfdb_path
                             .file_name()
                             .unwrap_or_default()
-                            .to_string_lossy()
-                            .to_string(),
-                        self.bfdata_path
-                            .clone()
-                            .unwrap_or_default()
+                            .to_string_lossy(),
+                        archive
+                            .bfdata_path
                             .file_name()
                             .unwrap_or_default()
                             .to_string_lossy()
-                            .to_string()
                     ));
+                } else if !self.archives.is_empty() {
+                    let names: Vec<_> = self.archives.iter().map(|a| a.name.as_str()).collect();
+                    ui.label(format!("{} bigfiles • {}", names.len(), names.join(", ")));
                 }
 
                 ui.with_layout(Layout::right_to_left(Align::RIGHT), |ui| {
-                    ui.label(format!("v{APP_VERSION}"))
+                    ui.label(format!("v{APP_VERSION}"));
+
+                    if !self.selected.is_empty() {
+                        let size = self.selected.iter().map(|f| f.size).sum();
+                        ui.separator();
+                        ui.label(format!(
+                            "{} selected • {}",
+                            self.selected.len(),
+                            format_size(size)
+                        ));
+                    }
                 });
             });
         });
     }
 
     fn display_preview(&mut self, ui: &mut Ui) {
-        if !self.selected.is_empty()
-            && let Some(image) = self.get_current_preview_file(ui)
-        {
-            ui.centered_and_justified(|ui| {
-                ui.image(ImageSource::Bytes {
-                    uri: format!("bytes://{}", &self.selected[0].path.to_string_lossy()).into(),
-                    bytes: image.into(),
-                })
+        if self.selected.is_empty() {
+            return;
+        }
+
+        let file = Arc::clone(&self.selected[0]);
+        let bytes = match self.get_current_preview_file(ui) {
+            Ok(v) => v,
+            Err(e) => {
+                ui.centered_and_justified(|ui| ui.label(format!("Couldn't read this file. {e}")));
+                return;
+            }
+        };
+
+        if bytes.is_empty() {
+            ui.centered_and_justified(|ui| ui.weak("This file is empty"));
+            return;
+        }
+
+        self.show_scrub_bar(ui, &file, bytes.len());
+        if let Some(offset) = self.scrub_offset {
+            self.show_scrub_preview(ui, &file, &bytes, offset);
+            return;
+        }
+
+        if preview::is_dds(&bytes) {
+            let texture = self.preview_texture.get_or_insert_with(|| {
+                let name = preview_uri(&file, self.preview_generation);
+                preview::decode_dds(&bytes)
+                    .map(|image| ui.ctx().load_texture(name, image, Default::default()))
+            });
+
+            match texture {
+                Ok(texture) => {
+                    let image = egui::Image::from_texture(SizedTexture::from_handle(texture))
+                        .shrink_to_fit();
+                    ui.centered_and_justified(|ui| ui.add(image));
+                }
+                Err(_) => self.show_unsupported_preview(ui, &file),
+            }
+            return;
+        }
+
+        if preview::is_image(&file.path, &bytes) {
+            let image = egui::Image::new(ImageSource::Bytes {
+                uri: preview_uri(&file, self.preview_generation).into(),
+                bytes: bytes.into(),
             });
+
+            if image.load_for_size(ui.ctx(), ui.available_size()).is_ok() {
+                ui.centered_and_justified(|ui| ui.add(image));
+            } else {
+                self.show_unsupported_preview(ui, &file);
+            }
+            return;
+        }
+
+        let shown = &bytes[..bytes.len().min(preview::PREVIEW_LIMIT)];
+        if shown.len() < bytes.len() {
+            ui.weak(format!(
+                "Showing the first {} of {}",
+                format_size(shown.len() as _),
+                format_size(bytes.len() as _)
+            ));
         }
+
+        match preview::as_text(shown) {
+            Some(text) => preview::show_text(ui, text),
+            None => preview::show_hex(ui, shown, 0),
+        }
+    }
+
+    fn toggle_grid(&mut self) {
+        self.view = match self.view {
+            View::Preview => View::Grid,
+            View::Grid => View::Preview,
+        };
     }
-    fn get_current_preview_file(&mut self, ui: &mut Ui) -> Option<Arc<[u8]>> {
-        if self.preview_image.0 == self.selected[0].path {
-            return Some(self.preview_image.1.clone());
+
+    fn display_grid(&mut self, ui: &mut Ui) {
+        let Some(dir) = self
+            .selected
+            .first()
+            .and_then(|file| file.path.parent())
+            .map(Path::to_path_buf)
+        else {
+            ui.centered_and_justified(|ui| ui.weak("Select a file to see the images next to it"));
+            return;
+        };
+
+        if self.grid.as_ref().is_none_or(|(shown, _)| *shown != dir) {
+            let mut images: Vec<_> = self
+                .tree
+                .dir_at(&dir)
+                .into_iter()
+                .flat_map(|d| &d.files)
+                .filter(|f| IMAGE_EXTENSIONS.contains(&f.extension.as_str()))
+                .cloned()
+                .collect();
+            images.sort_by(|a, b| a.name.cmp(&b.name));
+            self.grid = Some((dir.clone(), images));
         }
-        if let Some(bigfile) = &self.bigfile
-            && let Ok(image) = bigfile.get(&self.selected[0].path)
-        {
-            if self.preview_image.0.as_os_str().len() > 0 {
-                let key = format!("bytes://{}", self.preview_image.0.to_string_lossy());
-                ui.ctx().forget_image(&key);
+
+        let images = self.grid.as_ref().map(|(_, images)| images.clone());
+        let images = images.unwrap_or_default();
+        if images.is_empty() {
+            ui.centered_and_justified(|ui| ui.weak("There are no images in this directory"));
+            return;
+        }
+
+        self.thumbnails.poll(ui.ctx());
+
+        let text_height = ui.text_style_height(&egui::TextStyle::Body);
+        let tile = egui::vec2(
+            THUMBNAIL_SIZE as f32 + 8.0,
+            THUMBNAIL_SIZE as f32 + 12.0 + text_height,
+        );
+        let spacing = ui.spacing().item_spacing;
+        let columns = ((ui.available_width() + spacing.x) / (tile.x + spacing.x)).floor();
+        let columns = (columns as usize).max(1);
+        let rows = images.len().div_ceil(columns);
+
+        let mut clicked = None;
+        // Only the rows in view are laid out, so only their thumbnails get decoded
+        egui::ScrollArea::vertical()
+            .auto_shrink(false)
+            .show_rows(ui, tile.y, rows, |ui, range| {
+                for row in range {
+                    ui.horizontal(|ui| {
+                        for file in images.iter().skip(row * columns).take(columns) {
+                            if self.show_thumbnail(ui, file, tile).clicked() {
+                                clicked = Some(Arc::clone(file));
+                            }
+                        }
+                    });
+                }
+            });
+
+        if let Some(file) = clicked {
+            self.selected = vec![Arc::clone(&file)];
+            self.anchor = Some(Arc::clone(&file));
+            self.focus = Some(Focus::File(file));
+            self.scroll_to_focus = true;
+            self.view = View::Preview;
+        }
+    }
+
+    fn show_thumbnail(
+        &mut self,
+        ui: &mut Ui,
+        file: &Arc<File>,
+        tile: egui::Vec2,
+    ) -> egui::Response {
+        let (rect, response) = ui.allocate_exact_size(tile, egui::Sense::click());
+        let selected = self.selected.contains(file);
+
+        let visuals = ui.visuals();
+        let (fill, text_color) = if selected {
+            (visuals.selection.bg_fill, visuals.selection.stroke.color)
+        } else if response.hovered() {
+            (visuals.widgets.hovered.weak_bg_fill, visuals.text_color())
+        } else {
+            (egui::Color32::TRANSPARENT, visuals.text_color())
+        };
+        ui.painter().rect_filled(rect, 4.0, fill);
+
+        let image_rect = egui::Rect::from_min_size(
+            rect.min + egui::vec2(4.0, 4.0),
+            egui::Vec2::splat(THUMBNAIL_SIZE as f32),
+        );
+        let bigfile = Arc::clone(&self.archives[file.archive].bigfile);
+        match self.thumbnails.get(file, &bigfile) {
+            Some(Ok(texture)) => {
+                let size = texture.size_vec2();
+                let size = size * (THUMBNAIL_SIZE as f32 / size.max_elem()).min(1.0);
+                egui::Image::from_texture(SizedTexture::from_handle(texture))
+                    .paint_at(ui, egui::Rect::from_center_size(image_rect.center(), size));
+            }
+            Some(Err(_)) => {
+                ui.painter().text(
+                    image_rect.center(),
+                    Align2::CENTER_CENTER,
+                    "Cannot preview",
+                    egui::FontId::proportional(12.0),
+                    ui.visuals().weak_text_color(),
+                );
+            }
+            None => {
+                let spinner = image_rect.shrink(THUMBNAIL_SIZE as f32 / 3.0);
+                egui::Spinner::new().paint_at(ui, spinner);
+            }
+        }
+
+        let name = egui::WidgetText::from(file.name.as_str()).into_galley(
+            ui,
+            Some(TextWrapMode::Truncate),
+            tile.x - 8.0,
+            egui::TextStyle::Body,
+        );
+        let name_pos = egui::pos2(
+            rect.center().x - name.size().x / 2.0,
+            image_rect.max.y + 4.0,
+        );
+        ui.painter().galley(name_pos, name, text_color);
+
+        response.on_hover_text(&file.name)
+    }
+
+    fn show_scrub_bar(&mut self, ui: &mut Ui, file: &File, len: usize) {
+        ui.horizontal(|ui| {
+            let mut scrubbing = self.scrub_offset.is_some();
+            ui.checkbox(&mut scrubbing, "Scrub")
+                .on_hover_text("Look for images embedded anywhere in the file");
+
+            match (scrubbing, self.scrub_offset) {
+                (true, None) => self.scrub_offset = Some(0),
+                (false, Some(offset)) => {
+                    ui.ctx()
+                        .forget_image(&scrub_uri(file, self.preview_generation, offset));
+                    self.scrub_offset = None;
+                    self.scrub_image = None;
+                }
+                _ => {}
+            }
+
+            if let Some(offset) = &mut self.scrub_offset {
+                let previous = *offset;
+                ui.spacing_mut().slider_width = ui.available_width() - 120.0;
+                ui.add(egui::Slider::new(offset, 0..=len.saturating_sub(1)).text("Offset"));
+
+                if *offset != previous {
+                    ui.ctx()
+                        .forget_image(&scrub_uri(file, self.preview_generation, previous));
+                }
+            }
+        });
+    }
+
+    // Shows an image if one starts at the offset, and the bytes from there otherwise
+    fn show_scrub_preview(&mut self, ui: &mut Ui, file: &File, bytes: &[u8], offset: usize) {
+        let bytes = &bytes[offset.min(bytes.len())..];
+
+        if preview::is_image_data(bytes) {
+            let uri = scrub_uri(file, self.preview_generation, offset);
+            let data = match &self.scrub_image {
+                Some((cached, data)) if *cached == uri => Arc::clone(data),
+                _ => {
+                    let data: Arc<[u8]> = bytes.into();
+                    self.scrub_image = Some((uri.clone(), Arc::clone(&data)));
+                    data
+                }
+            };
+            let image = egui::Image::new(ImageSource::Bytes {
+                uri: uri.into(),
+                bytes: data.into(),
+            });
+
+            if image.load_for_size(ui.ctx(), ui.available_size()).is_ok() {
+                ui.centered_and_justified(|ui| ui.add(image));
+                return;
+            }
+        }
+
+        let shown = &bytes[..bytes.len().min(preview::PREVIEW_LIMIT)];
+        preview::show_hex(ui, shown, offset);
+    }
+
+    fn show_unsupported_preview(&mut self, ui: &mut Ui, file: &Arc<File>) {
+        let text = match file.path.extension() {
+            Some(ext) => format!(
+                "Cannot preview this file type ({}, .{})",
+                format_size(file.size),
+                ext.to_string_lossy()
+            ),
+            None => format!("Cannot preview this file type ({})", format_size(file.size)),
+        };
+
+        ui.vertical_centered(|ui| {
+            ui.add_space(ui.available_height() / 2.0 - 32.0);
+            ui.label(text);
+            ui.add_space(8.0);
+
+            if ui.button("Extract to view").clicked() {
+                self.selected = vec![Arc::clone(file)];
+                self.extract_selected();
+            }
+        });
+    }
+
+    fn get_current_preview_file(&mut self, ui: &mut Ui) -> bigfile::Result<Arc<[u8]>> {
+        let file = &self.selected[0];
+
+        if let Some((previewed, bytes)) = &self.preview_image {
+            if previewed == file {
+                return Ok(bytes.clone());
+            }
+
+            let generation = self.preview_generation;
+            ui.ctx().forget_image(&preview_uri(previewed, generation));
+
+            // Scrubbing stays on, but starts over for the next file
+            if let Some(offset) = &mut self.scrub_offset {
+                ui.ctx()
+                    .forget_image(&scrub_uri(previewed, generation, *offset));
+                *offset = 0;
             }
+        }
+
+        let ptr: Arc<[u8]> = self.archives[file.archive].bigfile.get(&file.path)?.into();
+        self.preview_image = Some((Arc::clone(file), ptr.clone()));
+        self.preview_texture = None;
+        self.preview_generation += 1;
+        Ok(ptr)
+    }
+
+    fn handle_dropped_files(&mut self, ctx: &Context) {
+        let dropped: Vec<PathBuf> = ctx.input(|i| {
+            i.raw
+                .dropped_files
+                .iter()
+                .filter_map(|f| f.path.clone())
+                .collect()
+        });
 
-            let ptr: Arc<[u8]> = image.into();
-            self.preview_image = (self.selected[0].path.clone(), ptr.clone());
-            return Some(ptr);
+        if !dropped.is_empty() {
+            self.open_files(dropped);
         }
-        None
     }
 
     fn handle_input(&mut self, ctx: &Context) {
+        self.handle_dropped_files(ctx);
+
+        // Don't steal Ctrl + A and such from the search box
+        let typing = ctx.wants_keyboard_input();
+
         ctx.input_mut(|i| {
             if i.consume_shortcut(&OPEN_SHORTCUT.shortcut) {
                 self.add_bigfile();
             }
 
-            if self.bigfile.is_some() && i.consume_shortcut(&CLOSE_SHORTCUT.shortcut) {
+            if !self.archives.is_empty() && i.consume_shortcut(&CLOSE_SHORTCUT.shortcut) {
                 self.unload_bigfile();
             }
 
-            if self.bigfile.is_some() && i.consume_shortcut(&EXTRACT_ALL_SHORTCUT.shortcut) {
+            if self.can_reload() && i.consume_shortcut(&RELOAD_SHORTCUT.shortcut) {
+                self.reload_bigfiles();
+            }
+
+            if !self.archives.is_empty() && i.consume_shortcut(&EXTRACT_ALL_SHORTCUT.shortcut) {
                 self.extract_all();
             }
 
@@ -552,16 +2105,104 @@ impl App {
             {
                 self.extract_selected();
             }
-        })
+
+            if !self.archives.is_empty() && i.consume_shortcut(&GRID_SHORTCUT.shortcut) {
+                self.toggle_grid();
+            }
+
+            // Has to come before select all, as its shortcut contains the select all one
+            if !typing && i.consume_shortcut(&DESELECT_ALL_SHORTCUT.shortcut) {
+                self.selected.clear();
+            }
+
+            if !typing
+                && !self.archives.is_empty()
+                && i.consume_shortcut(&SELECT_ALL_SHORTCUT.shortcut)
+            {
+                self.select_all();
+            }
+        });
+
+        if typing {
+            return;
+        }
+
+        let nav_keys = [
+            Key::ArrowDown,
+            Key::ArrowUp,
+            Key::ArrowLeft,
+            Key::ArrowRight,
+            Key::Enter,
+        ];
+        let pressed = ctx.input_mut(|i| {
+            nav_keys
+                .into_iter()
+                .filter(|&key| i.consume_key(Modifiers::NONE, key))
+                .collect::<Vec<_>>()
+        });
+
+        for key in pressed {
+            self.navigate(ctx, key);
+        }
     }
 }
 
+fn dir_is_open(ctx: &Context, id: Id) -> bool {
+    egui::collapsing_header::CollapsingState::load_with_default_open(ctx, id, false).is_open()
+}
+
+fn set_dir_open(ctx: &Context, id: Id, open: bool) {
+    let mut state =
+        egui::collapsing_header::CollapsingState::load_with_default_open(ctx, id, false);
+    state.set_open(open);
+    state.store(ctx);
+}
+
 fn open_extract_dialog() -> Option<PathBuf> {
     FileDialog::new()
         .set_title("Select extract directory")
         .pick_folder()
 }
 
+// Opens a folder in the file manager, or the folder a file is in with the file selected
+// where the file manager can do that
+fn reveal(path: &Path) -> io::Result<()> {
+    let mut command = if cfg!(target_os = "windows") {
+        let mut command = Command::new("explorer");
+        if path.is_dir() {
+            command.arg(path);
+        } else {
+            let mut select = OsString::from("/select,");
+            select.push(path);
+            command.arg(select);
+        }
+        command
+    } else if cfg!(target_os = "macos") {
+        let mut command = Command::new("open");
+        if !path.is_dir() {
+            command.arg("-R");
+        }
+        command.arg(path);
+        command
+    } else {
+        // There's no common way to select a file, so its folder is opened instead
+        let folder = if path.is_dir() {
+            path
+        } else {
+            path.parent().unwrap_or(path)
+        };
+        let mut command = Command::new("xdg-open");
+        command.arg(folder);
+        command
+    };
+
+    // Explorer exits with 1 even when it worked, so only starting it is checked.
+    // It's waited for on a thread of its own, so that it doesn't linger as a zombie.
+    let mut child = command.spawn()?;
+    thread::spawn(move || child.wait());
+    Ok(())
+}
+
 fn auto_open_or_dialog(bfn_path: &Path, ext: &str) -> Option<PathBuf> {
     let path = bfn_path.with_extension(ext);
 
@@ -579,6 +2220,21 @@ fn open_bigfile_dialog(extension: &str) -> Option<PathBuf> {
         .pick_file()
 }
 
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string()
+}
+
+fn part_name(part: Part) -> &'static str {
+    match part {
+        Part::Bfn => "bfn",
+        Part::Bfdb => "bfdb",
+        Part::Bfdata => "bfdata",
+    }
+}
+
 fn show_modal<T>(
     ctx: &Context,
     id: String,
@@ -594,18 +2250,32 @@ fn show_modal<T>(
 }
 
 impl eframe::App for App {
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        eframe::set_value(storage, RECENT_KEY, &self.recent);
+        eframe::set_value(storage, SORT_KEY, &self.sort);
+        eframe::set_value(storage, LOAD_POLICY_KEY, &self.load_policy);
+        eframe::set_value(storage, THEME_KEY, &self.theme);
+    }
+
     fn update(&mut self, ctx: &egui::Context, _: &mut eframe::Frame) {
+        self.poll_tree_build(ctx);
         self.handle_input(ctx);
         self.show_menu(ctx);
         self.show_bottom_panel(ctx);
 
-        if self.bigfile.is_some() {
+        if !self.archives.is_empty() {
             self.show_left_panel(ctx);
         }
+        if !self.selected.is_empty() {
+            self.show_details_panel(ctx);
+        }
 
         egui::CentralPanel::default().show(ctx, |ui| {
-            if self.bigfile.is_some() {
-                self.display_preview(ui);
+            if !self.archives.is_empty() {
+                match self.view {
+                    View::Preview => self.display_preview(ui),
+                    View::Grid => self.display_grid(ui),
+                }
             }
         });
         self.show_modals(ctx);
@@ -613,6 +2283,8 @@ impl eframe::App for App {
 }
 
 fn main() -> eframe::Result {
+    let args: Vec<PathBuf> = std::env::args_os().skip(1).map(PathBuf::from).collect();
+
     let options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default()
             .with_inner_size([1280.0, 720.0])
@@ -625,7 +2297,12 @@ fn main() -> eframe::Result {
         options,
         Box::new(|cc| {
             egui_extras::install_image_loaders(&cc.egui_ctx);
-            Ok(Box::<App>::default())
+
+            let mut app = App::new(cc);
+            if !args.is_empty() {
+                app.open_files(args);
+            }
+            Ok(Box::new(app))
         }),
     )
 }
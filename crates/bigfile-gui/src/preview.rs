@@ -0,0 +1,127 @@
+use eframe::egui::{self, ColorImage, Label, RichText, TextStyle, Ui};
+use image::RgbaImage;
+use std::path::Path;
+
+// Rendering a huge blob as text or hex is way too slow and nobody scrolls through it anyway
+pub(crate) const PREVIEW_LIMIT: usize = 64 * 1024;
+
+const HEX_ROW_LEN: usize = 16;
+
+// egui's image loaders don't know DDS, so those are decoded by hand.
+// Covers the BC1-BC7 formats (DXT1, DXT5 and so on) as well as uncompressed ones.
+pub(crate) fn is_dds(bytes: &[u8]) -> bool {
+    bytes.starts_with(b"DDS ")
+}
+
+pub(crate) fn decode_dds(bytes: &[u8]) -> Result<ColorImage, String> {
+    Ok(color_image(&dds_to_rgba(bytes)?))
+}
+
+fn dds_to_rgba(bytes: &[u8]) -> Result<RgbaImage, String> {
+    let dds = image_dds::ddsfile::Dds::read(bytes).map_err(|e| e.to_string())?;
+    image_dds::image_from_dds(&dds, 0).map_err(|e| e.to_string())
+}
+
+fn color_image(image: &RgbaImage) -> ColorImage {
+    let size = [image.width() as _, image.height() as _];
+    ColorImage::from_rgba_unmultiplied(size, image.as_raw())
+}
+
+// Decodes a DDS or anything the image crate knows and scales it down to fit in a `size` square
+pub(crate) fn decode_thumbnail(bytes: &[u8], size: u32) -> Result<ColorImage, String> {
+    let image = if is_dds(bytes) {
+        dds_to_rgba(bytes)?
+    } else {
+        image::load_from_memory(bytes)
+            .map_err(|e| e.to_string())?
+            .into_rgba8()
+    };
+
+    let (width, height) = image.dimensions();
+    if width <= size && height <= size {
+        return Ok(color_image(&image));
+    }
+
+    let scale = size as f32 / width.max(height) as f32;
+    let width = ((width as f32 * scale).round() as u32).max(1);
+    let height = ((height as f32 * scale).round() as u32).max(1);
+    Ok(color_image(&image::imageops::thumbnail(
+        &image, width, height,
+    )))
+}
+
+pub(crate) fn is_image(path: &Path, bytes: &[u8]) -> bool {
+    let is_svg = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("svg"));
+
+    !bytes.is_empty() && (is_svg || is_image_data(bytes))
+}
+
+// Whether the bytes start with something the image loaders understand
+pub(crate) fn is_image_data(bytes: &[u8]) -> bool {
+    image::guess_format(bytes).is_ok()
+}
+
+// Returns the text if the (possibly truncated) bytes are valid UTF-8
+pub(crate) fn as_text(bytes: &[u8]) -> Option<&str> {
+    match std::str::from_utf8(bytes) {
+        Ok(text) => Some(text),
+        // The preview might have cut a character in half, which is fine
+        Err(e) if e.error_len().is_none() => std::str::from_utf8(&bytes[..e.valid_up_to()]).ok(),
+        Err(_) => None,
+    }
+}
+
+pub(crate) fn show_text(ui: &mut Ui, text: &str) {
+    egui::ScrollArea::both().auto_shrink(false).show(ui, |ui| {
+        ui.add(Label::new(RichText::new(text).monospace()).extend());
+    });
+}
+
+// `base` is the offset of `bytes` into the file, for the offset column
+pub(crate) fn show_hex(ui: &mut Ui, bytes: &[u8], base: usize) {
+    let row_height = ui.text_style_height(&TextStyle::Monospace);
+    let rows = bytes.len().div_ceil(HEX_ROW_LEN);
+
+    egui::ScrollArea::both()
+        .auto_shrink(false)
+        .show_rows(ui, row_height, rows, |ui, range| {
+            for row in range {
+                let start = row * HEX_ROW_LEN;
+                let end = (start + HEX_ROW_LEN).min(bytes.len());
+                ui.add(
+                    Label::new(
+                        RichText::new(hex_line(base + start, &bytes[start..end])).monospace(),
+                    )
+                    .extend(),
+                );
+            }
+        });
+}
+
+fn hex_line(offset: usize, bytes: &[u8]) -> String {
+    let mut line = format!("{offset:08X}  ");
+
+    for i in 0..HEX_ROW_LEN {
+        match bytes.get(i) {
+            Some(b) => line.push_str(&format!("{b:02X} ")),
+            None => line.push_str("   "),
+        }
+
+        if i == HEX_ROW_LEN / 2 - 1 {
+            line.push(' ');
+        }
+    }
+
+    line.push(' ');
+    for &b in bytes {
+        line.push(if b.is_ascii_graphic() || b == b' ' {
+            b as char
+        } else {
+            '.'
+        });
+    }
+
+    line
+}
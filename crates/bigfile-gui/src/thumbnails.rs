@@ -0,0 +1,109 @@
+use crate::{File, preview};
+use bigfile::BigFile;
+use eframe::egui::{ColorImage, Context, TextureHandle};
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Arc,
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+pub(crate) const THUMBNAIL_SIZE: u32 = 128;
+
+// Each one is decoded on a thread of its own, big DDS textures take a while
+const MAX_DECODING: usize = 4;
+
+// The thumbnails shown longest ago are dropped past this many
+const CAPACITY: usize = 256;
+
+// Extensions worth decoding a thumbnail for
+pub(crate) const IMAGE_EXTENSIONS: [&str; 8] =
+    ["dds", "png", "jpg", "jpeg", "gif", "webp", "bmp", "tga"];
+
+// Thumbnails for the grid view, decoded as they're asked for instead of all at once
+#[derive(Default)]
+pub(crate) struct Thumbnails {
+    // By file id
+    loaded: HashMap<u64, Result<TextureHandle, String>>,
+    // File ids in the order they were last asked for, most recent last
+    order: VecDeque<u64>,
+    decoding: HashMap<u64, JoinHandle<Result<ColorImage, String>>>,
+}
+
+impl Thumbnails {
+    // Returns `None` while the thumbnail is still being decoded,
+    // and starts decoding it if there's room for another one
+    pub(crate) fn get(
+        &mut self,
+        file: &File,
+        bigfile: &Arc<BigFile>,
+    ) -> Option<&Result<TextureHandle, String>> {
+        self.touch(file.id);
+
+        if !self.loaded.contains_key(&file.id)
+            && !self.decoding.contains_key(&file.id)
+            && self.decoding.len() < MAX_DECODING
+        {
+            let bigfile = Arc::clone(bigfile);
+            let path = file.path.clone();
+            let handle = thread::spawn(move || {
+                let bytes = bigfile.get(&path).map_err(|e| e.to_string())?;
+                preview::decode_thumbnail(&bytes, THUMBNAIL_SIZE)
+            });
+
+            self.decoding.insert(file.id, handle);
+        }
+
+        self.loaded.get(&file.id)
+    }
+
+    // Uploads the thumbnails that finished decoding, once a frame
+    pub(crate) fn poll(&mut self, ctx: &Context) {
+        let finished: Vec<u64> = self
+            .decoding
+            .iter()
+            .filter(|(_, handle)| handle.is_finished())
+            .map(|(id, _)| *id)
+            .collect();
+
+        for id in finished {
+            let handle = self.decoding.remove(&id).unwrap();
+            let thumbnail = handle
+                .join()
+                .unwrap_or_else(|_| Err("Decoding the thumbnail panicked".to_string()))
+                .map(|image| {
+                    ctx.load_texture(format!("thumbnail://{id}"), image, Default::default())
+                });
+
+            // It may have scrolled out of the cache while it was decoding
+            if self.order.contains(&id) {
+                self.loaded.insert(id, thumbnail);
+            }
+        }
+
+        while self.order.len() > CAPACITY {
+            if let Some(id) = self.order.pop_front() {
+                self.loaded.remove(&id);
+            }
+        }
+
+        if !self.decoding.is_empty() {
+            ctx.request_repaint_after(Duration::from_millis(50));
+        }
+    }
+
+    // File ids change when the tree is rebuilt, so everything has to go.
+    // Threads that are still decoding finish on their own
+    pub(crate) fn clear(&mut self) {
+        self.loaded.clear();
+        self.order.clear();
+        self.decoding.clear();
+    }
+
+    fn touch(&mut self, id: u64) {
+        if let Some(i) = self.order.iter().position(|&other| other == id) {
+            self.order.remove(i);
+        }
+        self.order.push_back(id);
+    }
+}
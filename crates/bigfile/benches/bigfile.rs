@@ -0,0 +1,105 @@
+use std::{fs::File, hint::black_box, io::BufWriter, path::PathBuf};
+
+use bigfile::{BigFile, BigFileWriter, DataSource};
+use criterion::{BatchSize, Criterion, criterion_group, criterion_main};
+use tempfile::TempDir;
+
+const FILE_COUNT: usize = 2000;
+const GET_COUNT: usize = 10_000;
+
+// xorshift, so that the "random" paths are the same on every run
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+}
+
+// Writes a bigfile of `FILE_COUNT` files between 1 and 16 KiB spread over nested directories,
+// returns the directory it's in and the paths of its files
+fn synthetic_bigfile() -> (TempDir, Vec<PathBuf>) {
+    let dir = TempDir::new().unwrap();
+    let mut rng = Rng(0x2545F4914F6CDD1D);
+    let mut writer = BigFileWriter::new();
+    let mut paths = Vec::with_capacity(FILE_COUNT);
+
+    for i in 0..FILE_COUNT {
+        let path = PathBuf::from(format!("dir{}/sub{}/file{i}.bin", i % 16, i % 5));
+        let data = vec![i as u8; 1024 + (rng.next() % (15 * 1024)) as usize];
+        writer.add_file(path.clone(), &data).unwrap();
+        paths.push(PathBuf::from(".").join(path));
+    }
+
+    let create =
+        |ext| BufWriter::new(File::create(dir.path().join("bigfile").with_extension(ext)).unwrap());
+    writer
+        .write(
+            &mut create("bfn"),
+            &mut create("bfdb"),
+            &mut create("bfdata"),
+        )
+        .unwrap();
+
+    (dir, paths)
+}
+
+fn random_paths(paths: &[PathBuf]) -> Vec<PathBuf> {
+    let mut rng = Rng(0x9E3779B97F4A7C15);
+    (0..GET_COUNT)
+        .map(|_| paths[rng.next() as usize % paths.len()].clone())
+        .collect()
+}
+
+fn bench(c: &mut Criterion) {
+    let (dir, paths) = synthetic_bigfile();
+    let bfn = dir.path().join("bigfile.bfn");
+    let gets = random_paths(&paths);
+
+    c.bench_function("open", |b| b.iter(|| BigFile::open(bfn.clone()).unwrap()));
+
+    let bigfile = BigFile::open(bfn.clone()).unwrap();
+    c.bench_function("10k random gets (file)", |b| {
+        b.iter(|| {
+            for path in &gets {
+                black_box(bigfile.get(path).unwrap());
+            }
+        })
+    });
+
+    let buf = std::fs::read(dir.path().join("bigfile.bfdata")).unwrap();
+    let in_memory = BigFile::from_paths(
+        bfn.clone(),
+        dir.path().join("bigfile.bfdb"),
+        DataSource::Buffer(buf.into()),
+    )
+    .unwrap();
+    c.bench_function("10k random gets (buffer)", |b| {
+        b.iter(|| {
+            for path in &gets {
+                black_box(in_memory.get(path).unwrap());
+            }
+        })
+    });
+
+    let mut extract = c.benchmark_group("extract");
+    extract.sample_size(10);
+    extract.bench_function("extract", |b| {
+        b.iter_batched(
+            || TempDir::new().unwrap(),
+            // Returned so that removing the files isn't timed
+            |out| {
+                bigfile.extract(out.path().to_path_buf()).unwrap();
+                out
+            },
+            BatchSize::PerIteration,
+        )
+    });
+    extract.finish();
+}
+
+criterion_group!(benches, bench);
+criterion_main!(benches);
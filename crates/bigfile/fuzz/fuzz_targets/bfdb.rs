@@ -0,0 +1,10 @@
+#![no_main]
+
+use std::io::Cursor;
+
+use bigfile::bfdb::Bfdb;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = Bfdb::read(&mut Cursor::new(data));
+});
@@ -0,0 +1,11 @@
+#![no_main]
+
+use std::io::Cursor;
+
+use bigfile::bfn::Bfn;
+use libfuzzer_sys::fuzz_target;
+
+// Anything that isn't a bfn has to be an error, not a panic or a huge allocation
+fuzz_target!(|data: &[u8]| {
+    let _ = Bfn::read(&mut Cursor::new(data));
+});
@@ -0,0 +1,31 @@
+#![no_main]
+
+use std::io::Cursor;
+
+use bigfile::BigFile;
+use libfuzzer_sys::fuzz_target;
+
+// The input is split into a bfn, bfdb and bfdata at the offsets in its first two bytes,
+// so that the parsers are fuzzed together with the matching of paths to entries
+fuzz_target!(|data: &[u8]| {
+    let [a, b, rest @ ..] = data else {
+        return;
+    };
+
+    let bfn_len = (*a as usize).min(rest.len());
+    let (bfn, rest) = rest.split_at(bfn_len);
+    let bfdb_len = (*b as usize).min(rest.len());
+    let (bfdb, bfdata) = rest.split_at(bfdb_len);
+
+    let Ok(bigfile) = BigFile::new(
+        &mut Cursor::new(bfn),
+        &mut Cursor::new(bfdb),
+        &mut Cursor::new(bfdata),
+    ) else {
+        return;
+    };
+
+    for path in bigfile.paths() {
+        let _ = bigfile.get(path);
+    }
+});
@@ -1,33 +1,66 @@
 use std::{
     collections::HashMap,
-    io::{Read, Seek},
+    io::{Read, Seek, Write},
 };
 
-use crate::{error::Result, reader::BigFileReader};
+use bigfile_core::Endianness;
+pub use bigfile_core::bfdb::Entry;
 
-#[derive(Clone, Copy)]
-pub(crate) struct Entry {
-    pub offset: u64,
-    pub size: u64,
-}
+use crate::{BFDB_LABEL, Progress, error::Result, reader::BigFileReader};
 
-pub(crate) struct Bfdb {
+/// A parsed `.bfdb` file. `entries` is for looking entries up by hash,
+/// `records` keeps them in the order they were in the file.
+pub struct Bfdb {
     pub entries: HashMap<u64, Entry>,
+    pub records: Vec<(u64, Entry)>,
 }
 
 impl Bfdb {
-    pub(crate) fn from(reader: &mut BigFileReader<impl Read + Seek>) -> Result<Self> {
-        let len = reader.read_u32_le()?;
-        let mut entries = HashMap::with_capacity(len as _);
+    /// Reads a little-endian bfdb, see `read_with_endianness`.
+    pub fn read<R: Read + Seek>(reader: &mut R) -> Result<Self> {
+        Bfdb::read_with_endianness(reader, Endianness::Little)
+    }
+
+    pub fn read_with_endianness<R: Read + Seek>(
+        reader: &mut R,
+        endianness: Endianness,
+    ) -> Result<Self> {
+        let mut reader = BigFileReader::new(reader)
+            .label(BFDB_LABEL)
+            .endianness(endianness);
+        Bfdb::from(&mut reader, &mut |_| {})
+    }
 
-        for _ in 0..len {
-            let size = reader.read_u64_le()?;
-            let offset = reader.read_u64_le()?;
-            let hash = reader.read_u64_le()?;
+    /// Writes the records back out in order, little-endian. Writing a bfdb that was just
+    /// read gives the same bytes, as long as it's written in the byte order it was read in.
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<()> {
+        self.write_with_endianness(writer, Endianness::Little)
+    }
 
-            entries.insert(hash, Entry { offset, size });
+    pub fn write_with_endianness<W: Write>(
+        &self,
+        writer: &mut W,
+        endianness: Endianness,
+    ) -> Result<()> {
+        writer.write_all(&endianness.u32_bytes(self.records.len() as u32))?;
+        for (hash, entry) in &self.records {
+            writer.write_all(&endianness.u64_bytes(entry.size))?;
+            writer.write_all(&endianness.u64_bytes(entry.offset))?;
+            writer.write_all(&endianness.u64_bytes(*hash))?;
         }
 
-        Ok(Bfdb { entries })
+        Ok(())
+    }
+
+    pub(crate) fn from(
+        reader: &mut BigFileReader<impl Read + Seek>,
+        progress: &mut impl FnMut(Progress),
+    ) -> Result<Self> {
+        let records = bigfile_core::bfdb::read_bfdb(reader, &mut |read, total| {
+            progress(Progress::Entries { read, total })
+        })?;
+        let entries = records.iter().copied().collect();
+
+        Ok(Bfdb { entries, records })
     }
 }
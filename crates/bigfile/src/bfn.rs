@@ -1,46 +1,114 @@
-use crate::{error::Result, reader::BigFileReader};
+use crate::{BFN_LABEL, Progress, error::Result, reader::BigFileReader};
+pub use bigfile_core::bfn::BfnDir;
+use bigfile_core::{Endianness, bfn::BfnVisitor};
 use std::{
-    io::{Read, Seek},
-    path::PathBuf,
+    io::{Read, Seek, Write},
+    path::{Path, PathBuf},
 };
 
-pub(crate) struct Bfn {
-    pub(crate) files: Vec<PathBuf>,
+/// A parsed `.bfn` file: the directory tree as it's laid out in the file,
+/// and the full path of every file in it, in the same order.
+pub struct Bfn {
+    pub files: Vec<PathBuf>,
+    pub root: BfnDir,
 }
 
 impl Bfn {
-    pub(crate) fn from(reader: &mut BigFileReader<impl Read + Seek>) -> Result<Self> {
+    /// Reads a little-endian bfn, see `read_with_endianness`.
+    pub fn read<R: Read + Seek>(reader: &mut R) -> Result<Self> {
+        Bfn::read_with_endianness(reader, Endianness::Little)
+    }
+
+    pub fn read_with_endianness<R: Read + Seek>(
+        reader: &mut R,
+        endianness: Endianness,
+    ) -> Result<Self> {
+        let mut reader = BigFileReader::new(reader)
+            .label(BFN_LABEL)
+            .endianness(endianness);
+        Bfn::from(&mut reader, &mut |_| {})
+    }
+
+    /// Like `read`, but hands each file's full path to `on_file` as soon as it's read
+    /// instead of collecting them, e.g. to fill a tree while a huge bfn is still being read.
+    /// Only the directory tree is returned.
+    pub fn read_each<R: Read + Seek>(
+        reader: &mut R,
+        mut on_file: impl FnMut(&Path),
+    ) -> Result<BfnDir> {
+        let mut visitor = FilePaths::new(|path: PathBuf| on_file(&path));
+        let mut reader = BigFileReader::new(reader).label(BFN_LABEL);
+        bigfile_core::bfn::read_bfn(&mut reader, &mut visitor)
+    }
+
+    /// Writes the bfn back out, little-endian. Writing a bfn that was just read gives
+    /// the same bytes, as long as it's written in the byte order it was read in.
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<()> {
+        self.write_with_endianness(writer, Endianness::Little)
+    }
+
+    pub fn write_with_endianness<W: Write>(
+        &self,
+        writer: &mut W,
+        endianness: Endianness,
+    ) -> Result<()> {
+        Ok(self.root.write_with_endianness(writer, endianness)?)
+    }
+
+    pub(crate) fn from(
+        reader: &mut BigFileReader<impl Read + Seek>,
+        progress: &mut impl FnMut(Progress),
+    ) -> Result<Self> {
         let mut files = Vec::new();
+        let mut visitor = FilePaths::new(|path| {
+            files.push(path);
+            progress(Progress::Names {
+                read: files.len() as _,
+            });
+        });
+
+        let root = bigfile_core::bfn::read_bfn(reader, &mut visitor)?;
+        log::debug!("bfn has {} files", files.len());
 
-        fn read_dir(
-            reader: &mut BigFileReader<impl Read + Seek>,
-            parent: &PathBuf,
-            out: &mut Vec<PathBuf>,
-        ) -> Result<()> {
-            let name_len = reader.read_u32_le()?;
-            let name = reader.read_string(name_len as _)?;
-            let mut cur_path = parent.clone();
-            cur_path.push(name);
-
-            let file_count = reader.read_u32_le()?;
-            for _ in 0..file_count {
-                let len = reader.read_u32_le()?;
-                let file_name = reader.read_string(len as _)?;
-                let mut file_path = cur_path.clone();
-                file_path.push(file_name);
-                out.push(file_path);
-            }
-
-            let subdir_count = reader.read_u32_le()?;
-            for _ in 0..subdir_count {
-                read_dir(reader, &cur_path, out)?;
-            }
-            Ok(())
+        Ok(Bfn { files, root })
+    }
+}
+
+// Builds the path of every file as the bfn is read
+struct FilePaths<F> {
+    // The path of every directory that's being read, innermost last
+    dirs: Vec<PathBuf>,
+    on_file: F,
+}
+
+impl<F: FnMut(PathBuf)> FilePaths<F> {
+    fn new(on_file: F) -> Self {
+        FilePaths {
+            dirs: Vec::new(),
+            on_file,
         }
+    }
 
-        let root = PathBuf::new();
-        read_dir(reader, &root, &mut files)?;
+    fn join(&self, name: &str) -> PathBuf {
+        let mut path = self.dirs.last().cloned().unwrap_or_default();
+        path.push(name);
+        path
+    }
+}
+
+impl<F: FnMut(PathBuf)> BfnVisitor for FilePaths<F> {
+    fn enter_dir(&mut self, name: &str) {
+        let path = self.join(name);
+        log::trace!("reading bfn directory {}", path.display());
+        self.dirs.push(path);
+    }
+
+    fn file(&mut self, name: &str) {
+        let path = self.join(name);
+        (self.on_file)(path);
+    }
 
-        Ok(Bfn { files })
+    fn leave_dir(&mut self) {
+        self.dirs.pop();
     }
 }
@@ -0,0 +1,190 @@
+// An index cache keeps what `Index::build` worked out from a bfn and bfdb in a file of
+// its own, so that opening the same bigfile again doesn't parse and hash everything.
+// See `BigFileOptions::index_cache`.
+//
+// All little-endian: the magic, a u32 version, the `Key` as u64s and the bfn's root
+// directory as a path. Then a u64 count of entries, each as its offset and size followed
+// by its path, and a u64 count of hashes, each followed by the path it leads to. Paths are
+// a u32 length and UTF-8. Then a u64 count of orphans and their hashes, and last a u64
+// count of bfdb records, each as its hash, offset and size.
+
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+use bigfile_core::{Endianness, SliceSource, Source};
+
+use crate::{BigFileOptions, Entry, Index};
+
+const MAGIC: &[u8; 4] = b"BFIX";
+const VERSION: u32 = 3;
+
+// Sizes and modification times of the bfn and bfdb, and the options that change what's
+// parsed out of them. The cache is only used if all of it is the same as when it was written.
+pub(crate) type Key = [u64; 10];
+
+// `None` if either file's modification time can't be read, in which case there's no
+// telling whether it changed and the cache isn't used
+pub(crate) fn key(bfn: &Path, bfdb: &Path, options: &BigFileOptions) -> Option<Key> {
+    let [bfn_len, bfn_secs, bfn_nanos] = stamp(bfn)?;
+    let [bfdb_len, bfdb_secs, bfdb_nanos] = stamp(bfdb)?;
+
+    Some([
+        bfn_len,
+        bfn_secs,
+        bfn_nanos,
+        bfdb_len,
+        bfdb_secs,
+        bfdb_nanos,
+        options.root_strip as u64,
+        options.max_depth as u64,
+        options.lossy_strings as u64,
+        (options.endianness == Endianness::Big) as u64,
+    ])
+}
+
+fn stamp(path: &Path) -> Option<[u64; 3]> {
+    let metadata = fs::metadata(path).ok()?;
+    let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+    Some([
+        metadata.len(),
+        modified.as_secs(),
+        modified.subsec_nanos() as u64,
+    ])
+}
+
+// `None` if there's no cache, it's for other files or options, or it can't be read
+pub(crate) fn load(path: &Path, key: &Key) -> Option<Index> {
+    let data = fs::read(path).ok()?;
+    let Some(rest) = data.strip_prefix(MAGIC) else {
+        log::debug!("{} isn't an index cache", path.display());
+        return None;
+    };
+
+    match read(&mut SliceSource::new(rest), key) {
+        Ok(Some(index)) => {
+            log::debug!("loaded the index from {}", path.display());
+            Some(index)
+        }
+        Ok(None) => {
+            log::debug!("{} is out of date", path.display());
+            None
+        }
+        Err(err) => {
+            log::debug!("couldn't read the index cache {}: {err}", path.display());
+            None
+        }
+    }
+}
+
+fn read(source: &mut SliceSource, key: &Key) -> Result<Option<Index>, bigfile_core::ParseError> {
+    if source.read_u32()? != VERSION {
+        return Ok(None);
+    }
+
+    for expected in key {
+        if source.read_u64()? != *expected {
+            return Ok(None);
+        }
+    }
+
+    let root = read_path(source)?;
+
+    // Capacities are capped by what's left, so that a corrupt count can't allocate much
+    let count = source.read_u64()?;
+    let mut entries = HashMap::with_capacity(count.min(source.remaining()? / 20) as usize);
+    for _ in 0..count {
+        let offset = source.read_u64()?;
+        let size = source.read_u64()?;
+        entries.insert(read_path(source)?, Entry { offset, size });
+    }
+
+    let count = source.read_u64()?;
+    let mut hashes = HashMap::with_capacity(count.min(source.remaining()? / 12) as usize);
+    for _ in 0..count {
+        let hash = source.read_u64()?;
+        hashes.insert(hash, read_path(source)?);
+    }
+
+    let count = source.read_u64()?;
+    let mut orphans = Vec::with_capacity(count.min(source.remaining()? / 8) as usize);
+    for _ in 0..count {
+        orphans.push(source.read_u64()?);
+    }
+
+    let count = source.read_u64()?;
+    let mut records = Vec::with_capacity(count.min(source.remaining()? / 24) as usize);
+    for _ in 0..count {
+        let hash = source.read_u64()?;
+        let offset = source.read_u64()?;
+        let size = source.read_u64()?;
+        records.push((hash, Entry { offset, size }));
+    }
+
+    Ok(Some(Index {
+        entries,
+        hashes,
+        orphans,
+        records,
+        root,
+    }))
+}
+
+fn read_path(source: &mut SliceSource) -> Result<PathBuf, bigfile_core::ParseError> {
+    let len = source.read_u32()? as usize;
+    Ok(PathBuf::from(source.read_string(len)?))
+}
+
+pub(crate) fn store(path: &Path, key: &Key, index: &Index) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    out.write_all(MAGIC)?;
+    out.write_all(&VERSION.to_le_bytes())?;
+    for value in key {
+        out.write_all(&value.to_le_bytes())?;
+    }
+    write_path(&mut out, &index.root)?;
+
+    out.write_all(&(index.entries.len() as u64).to_le_bytes())?;
+    for (entry_path, entry) in &index.entries {
+        out.write_all(&entry.offset.to_le_bytes())?;
+        out.write_all(&entry.size.to_le_bytes())?;
+        write_path(&mut out, entry_path)?;
+    }
+
+    out.write_all(&(index.hashes.len() as u64).to_le_bytes())?;
+    for (hash, entry_path) in &index.hashes {
+        out.write_all(&hash.to_le_bytes())?;
+        write_path(&mut out, entry_path)?;
+    }
+
+    out.write_all(&(index.orphans.len() as u64).to_le_bytes())?;
+    for hash in &index.orphans {
+        out.write_all(&hash.to_le_bytes())?;
+    }
+
+    out.write_all(&(index.records.len() as u64).to_le_bytes())?;
+    for (hash, entry) in &index.records {
+        out.write_all(&hash.to_le_bytes())?;
+        out.write_all(&entry.offset.to_le_bytes())?;
+        out.write_all(&entry.size.to_le_bytes())?;
+    }
+
+    out.flush()
+}
+
+fn write_path(out: &mut impl Write, path: &Path) -> io::Result<()> {
+    // Paths come from the bfn, which only has UTF-8 in it
+    let Some(path) = path.to_str() else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{} isn't valid UTF-8", path.display()),
+        ));
+    };
+
+    out.write_all(&(path.len() as u32).to_le_bytes())?;
+    out.write_all(path.as_bytes())
+}
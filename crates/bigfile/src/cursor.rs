@@ -0,0 +1,95 @@
+#[cfg(feature = "fs")]
+use std::io::BufReader;
+use std::{
+    io::{self, Cursor, Read, Seek, SeekFrom},
+    sync::Arc,
+};
+
+#[cfg(feature = "fs")]
+use crate::reader::SharedFile;
+
+pub(crate) enum CursorSource {
+    #[cfg(feature = "fs")]
+    File(BufReader<SharedFile>),
+    Buffer(Cursor<Arc<[u8]>>),
+}
+
+/// A single entry as a standalone stream: position 0 is the start of the entry
+/// and the stream ends where the entry does. Seeking past the end stops at the end,
+/// so neighbouring entries can't be read through it.
+pub struct EntryCursor {
+    inner: CursorSource,
+    // Where the entry starts in `inner`
+    start: u64,
+    len: u64,
+    pos: u64,
+}
+
+impl EntryCursor {
+    pub(crate) fn new(mut inner: CursorSource, start: u64, len: u64) -> io::Result<Self> {
+        match &mut inner {
+            #[cfg(feature = "fs")]
+            CursorSource::File(reader) => reader.seek(SeekFrom::Start(start))?,
+            CursorSource::Buffer(cursor) => cursor.seek(SeekFrom::Start(start))?,
+        };
+
+        Ok(EntryCursor {
+            inner,
+            start,
+            len,
+            pos: 0,
+        })
+    }
+
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+impl Read for EntryCursor {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let left = (self.len - self.pos).min(buf.len() as u64) as usize;
+        let buf = &mut buf[..left];
+
+        let read = match &mut self.inner {
+            #[cfg(feature = "fs")]
+            CursorSource::File(reader) => reader.read(buf)?,
+            CursorSource::Buffer(cursor) => cursor.read(buf)?,
+        };
+
+        self.pos += read as u64;
+        Ok(read)
+    }
+}
+
+impl Seek for EntryCursor {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let target = match pos {
+            SeekFrom::Start(v) => Some(v),
+            SeekFrom::End(v) => self.len.checked_add_signed(v),
+            SeekFrom::Current(v) => self.pos.checked_add_signed(v),
+        };
+
+        let Some(target) = target else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "can't seek before the start of the entry",
+            ));
+        };
+
+        let target = target.min(self.len);
+        let inner_pos = SeekFrom::Start(self.start + target);
+        match &mut self.inner {
+            #[cfg(feature = "fs")]
+            CursorSource::File(reader) => reader.seek(inner_pos)?,
+            CursorSource::Buffer(cursor) => cursor.seek(inner_pos)?,
+        };
+
+        self.pos = target;
+        Ok(target)
+    }
+}
@@ -0,0 +1,161 @@
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    slice, vec,
+};
+
+use crate::{BigFile, Entry, crc32::Crc32, format_size};
+
+/// How a single path differs between two bigfiles, see `BigFile::diff`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Change {
+    /// Only in the newer bigfile
+    Added(PathBuf),
+    /// Only in the older bigfile
+    Removed(PathBuf),
+    Resized {
+        path: PathBuf,
+        old: u64,
+        new: u64,
+    },
+    /// Same size, but different data. Only `BigFile::diff_contents` finds these
+    Modified(PathBuf),
+}
+
+impl Change {
+    pub fn path(&self) -> &Path {
+        match self {
+            Change::Added(path) | Change::Removed(path) | Change::Modified(path) => path,
+            Change::Resized { path, .. } => path,
+        }
+    }
+}
+
+// `+ path` for added, `- path` for removed and `~ path` for changed entries,
+// with the sizes for resized ones
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Change::Added(path) => write!(f, "+ {}", path.display()),
+            Change::Removed(path) => write!(f, "- {}", path.display()),
+            Change::Resized { path, old, new } => write!(
+                f,
+                "~ {} ({} -> {})",
+                path.display(),
+                format_size(*old),
+                format_size(*new)
+            ),
+            Change::Modified(path) => write!(f, "~ {}", path.display()),
+        }
+    }
+}
+
+/// Every change between two bigfiles, sorted by path. Displays as one change per line.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Diff {
+    changes: Vec<Change>,
+}
+
+impl Diff {
+    pub fn iter(&self) -> slice::Iter<'_, Change> {
+        self.changes.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.changes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+}
+
+impl IntoIterator for Diff {
+    type Item = Change;
+    type IntoIter = vec::IntoIter<Change>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.changes.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a Diff {
+    type Item = &'a Change;
+    type IntoIter = slice::Iter<'a, Change>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.changes.iter()
+    }
+}
+
+impl fmt::Display for Diff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, change) in self.changes.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{change}")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl BigFile {
+    /// What changed from this bigfile to `other`, going by entry sizes only. Paths are
+    /// matched like `find` does, so casing and slashes don't count as changes.
+    pub fn diff(&self, other: &BigFile) -> Diff {
+        self.diff_with(other, |_, _, _| Ok(false))
+            .expect("comparing sizes doesn't read anything")
+    }
+
+    /// Like `diff`, but entries of the same size are also read from both bigfiles
+    /// and compared by CRC-32, to find the ones that changed without changing size.
+    pub fn diff_contents(&self, other: &BigFile) -> crate::Result<Diff> {
+        self.diff_with(other, |path, entry, (other_path, other_entry)| {
+            let crc = |bigfile: &BigFile, path: &Path, entry: &Entry| {
+                let mut crc = Crc32::new();
+                bigfile.copy_entry(path, entry, &mut crc)?;
+                crate::Result::Ok(crc.finish())
+            };
+
+            Ok(crc(self, path, entry)? != crc(other, other_path, other_entry)?)
+        })
+    }
+
+    // `modified` is only called for entries that are in both and have the same size
+    fn diff_with(
+        &self,
+        other: &BigFile,
+        mut modified: impl FnMut(&Path, &Entry, (&Path, &Entry)) -> crate::Result<bool>,
+    ) -> crate::Result<Diff> {
+        let mut changes = Vec::new();
+
+        for (path, entry) in &self.entries {
+            let Some(other_path) = other.find(path) else {
+                changes.push(Change::Removed(path.clone()));
+                continue;
+            };
+
+            let other_entry = &other.entries[other_path];
+            if entry.size != other_entry.size {
+                changes.push(Change::Resized {
+                    path: path.clone(),
+                    old: entry.size,
+                    new: other_entry.size,
+                });
+            } else if modified(path, entry, (other_path, other_entry))? {
+                changes.push(Change::Modified(path.clone()));
+            }
+        }
+
+        for path in other.entries.keys() {
+            if self.find(path).is_none() {
+                changes.push(Change::Added(path.clone()));
+            }
+        }
+
+        changes.sort_by(|a, b| a.path().cmp(b.path()));
+        Ok(Diff { changes })
+    }
+}
@@ -8,14 +8,54 @@ pub enum BigFileError {
         err: io::Error,
     },
     EntryNotFound(PathBuf),
+    // A bfdb or bfdata that was expected next to the bfn
+    SiblingNotFound(PathBuf),
     HashEntryNotFound(u64),
+    // Every bfn path that had no bfdb entry, out of `total` paths
+    MissingEntries {
+        missing: Vec<PathBuf>,
+        total: usize,
+    },
+    UnsafePath(PathBuf),
+    ReplacementTooLarge {
+        file: PathBuf,
+        size: u64,
+        available: u64,
+    },
+    // The bfdata ended before the entry did, it's cut short or doesn't belong to the bfdb
+    Truncated {
+        path: PathBuf,
+        offset: u64,
+        wanted: u64,
+        available: u64,
+    },
+    // Paths are hashed as UTF-8, so one that isn't can't be looked up in the bfdb
+    NonUtf8Path(PathBuf),
+    // A path added to a `BigFileWriter` that doesn't name a file, e.g. `` or `.`
+    EmptyPath(PathBuf),
+    // A path added to a `BigFileWriter` that only differs in casing or slashes from one
+    // that's already in, so both would have the same hash
+    ConflictingPath {
+        path: PathBuf,
+        existing: PathBuf,
+    },
 }
 
 pub type Result<T> = core::result::Result<T, BigFileError>;
 
+impl BigFileError {
+    /// The kind of the underlying io error, or `None` if this isn't an io error
+    pub fn io_kind(&self) -> Option<io::ErrorKind> {
+        match self {
+            BigFileError::Io { err, .. } => Some(err.kind()),
+            _ => None,
+        }
+    }
+}
+
 impl fmt::Display for BigFileError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        return match self {
+        match self {
             BigFileError::Io { file, offset, err } => {
                 if let Some(file) = file {
                     write!(f, "{}", file.display())?;
@@ -28,10 +68,190 @@ impl fmt::Display for BigFileError {
                 write!(f, ": {}", err)
             }
             BigFileError::EntryNotFound(p) => write!(f, "Couldn't find the entry {}", p.display()),
+            BigFileError::SiblingNotFound(p) => {
+                write!(f, "Couldn't find {} next to the bfn", p.display())
+            }
             BigFileError::HashEntryNotFound(hash) => {
                 write!(f, "Couldn't find an entry for hash {hash:X}")
             }
-        };
+            BigFileError::MissingEntries { missing, total } => {
+                write!(
+                    f,
+                    "{} of {total} bfn paths had no bfdb entry:",
+                    missing.len()
+                )?;
+
+                for path in missing {
+                    write!(f, "\n{}", path.display())?;
+                }
+
+                Ok(())
+            }
+            BigFileError::UnsafePath(p) => write!(
+                f,
+                "Refusing to extract {}, as it would end up outside the output directory",
+                p.display()
+            ),
+            BigFileError::ReplacementTooLarge {
+                file,
+                size,
+                available,
+            } => write!(
+                f,
+                "Can't replace {} in place: the new data is {size} bytes, \
+                but only {available} bytes are available. Repack the bigfile instead",
+                file.display()
+            ),
+            BigFileError::Truncated {
+                path,
+                offset,
+                wanted,
+                available,
+            } => write!(
+                f,
+                "{} is truncated: it needs {wanted} bytes at offset {offset}, \
+                but the bfdata only has {available} bytes left. \
+                The bfdata may be incomplete or not match the bfdb",
+                path.display()
+            ),
+            BigFileError::NonUtf8Path(p) => {
+                write!(
+                    f,
+                    "{} isn't valid UTF-8, so it can't be hashed",
+                    p.display()
+                )
+            }
+            BigFileError::EmptyPath(p) => {
+                write!(
+                    f,
+                    "{:?} doesn't name a file to add",
+                    p.display().to_string()
+                )
+            }
+            BigFileError::ConflictingPath { path, existing } => write!(
+                f,
+                "Can't add {}, it has the same hash as {}, which is already added",
+                path.display(),
+                existing.display()
+            ),
+        }
+    }
+}
+
+// `io::Error` isn't `Clone`, so the clone keeps only its kind and message
+impl Clone for BigFileError {
+    fn clone(&self) -> Self {
+        match self {
+            BigFileError::Io { file, offset, err } => BigFileError::Io {
+                file: file.clone(),
+                offset: *offset,
+                err: io::Error::new(err.kind(), err.to_string()),
+            },
+            BigFileError::EntryNotFound(p) => BigFileError::EntryNotFound(p.clone()),
+            BigFileError::SiblingNotFound(p) => BigFileError::SiblingNotFound(p.clone()),
+            BigFileError::HashEntryNotFound(hash) => BigFileError::HashEntryNotFound(*hash),
+            BigFileError::MissingEntries { missing, total } => BigFileError::MissingEntries {
+                missing: missing.clone(),
+                total: *total,
+            },
+            BigFileError::UnsafePath(p) => BigFileError::UnsafePath(p.clone()),
+            BigFileError::ReplacementTooLarge {
+                file,
+                size,
+                available,
+            } => BigFileError::ReplacementTooLarge {
+                file: file.clone(),
+                size: *size,
+                available: *available,
+            },
+            BigFileError::Truncated {
+                path,
+                offset,
+                wanted,
+                available,
+            } => BigFileError::Truncated {
+                path: path.clone(),
+                offset: *offset,
+                wanted: *wanted,
+                available: *available,
+            },
+            BigFileError::NonUtf8Path(p) => BigFileError::NonUtf8Path(p.clone()),
+            BigFileError::EmptyPath(p) => BigFileError::EmptyPath(p.clone()),
+            BigFileError::ConflictingPath { path, existing } => BigFileError::ConflictingPath {
+                path: path.clone(),
+                existing: existing.clone(),
+            },
+        }
+    }
+}
+
+// io errors are compared by kind, as `io::Error` isn't `PartialEq`
+impl PartialEq for BigFileError {
+    fn eq(&self, other: &Self) -> bool {
+        use BigFileError::*;
+
+        match (self, other) {
+            (
+                Io { file, offset, err },
+                Io {
+                    file: other_file,
+                    offset: other_offset,
+                    err: other_err,
+                },
+            ) => file == other_file && offset == other_offset && err.kind() == other_err.kind(),
+            (EntryNotFound(a), EntryNotFound(b)) => a == b,
+            (SiblingNotFound(a), SiblingNotFound(b)) => a == b,
+            (HashEntryNotFound(a), HashEntryNotFound(b)) => a == b,
+            (
+                MissingEntries { missing, total },
+                MissingEntries {
+                    missing: other_missing,
+                    total: other_total,
+                },
+            ) => missing == other_missing && total == other_total,
+            (UnsafePath(a), UnsafePath(b)) => a == b,
+            (
+                ReplacementTooLarge {
+                    file,
+                    size,
+                    available,
+                },
+                ReplacementTooLarge {
+                    file: other_file,
+                    size: other_size,
+                    available: other_available,
+                },
+            ) => file == other_file && size == other_size && available == other_available,
+            (
+                Truncated {
+                    path,
+                    offset,
+                    wanted,
+                    available,
+                },
+                Truncated {
+                    path: other_path,
+                    offset: other_offset,
+                    wanted: other_wanted,
+                    available: other_available,
+                },
+            ) => {
+                path == other_path
+                    && offset == other_offset
+                    && wanted == other_wanted
+                    && available == other_available
+            }
+            (NonUtf8Path(a), NonUtf8Path(b)) => a == b,
+            (EmptyPath(a), EmptyPath(b)) => a == b,
+            (
+                ConflictingPath { path, existing },
+                ConflictingPath {
+                    path: other_path,
+                    existing: other_existing,
+                },
+            ) => path == other_path && existing == other_existing,
+            _ => false,
+        }
     }
 }
 
@@ -45,14 +265,23 @@ impl From<io::Error> for BigFileError {
     }
 }
 
-impl std::error::Error for BigFileError {}
+impl std::error::Error for BigFileError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            BigFileError::Io { err, .. } => Some(err),
+            _ => None,
+        }
+    }
+}
 
 pub(crate) trait IoErrorExt {
+    #[cfg(feature = "fs")]
     fn with_file(self, file: PathBuf) -> BigFileError;
     fn with_offset(self, file: Option<PathBuf>, offset: Option<usize>) -> BigFileError;
 }
 
 impl IoErrorExt for io::Error {
+    #[cfg(feature = "fs")]
     fn with_file(self, file: PathBuf) -> BigFileError {
         BigFileError::Io {
             file: Some(file),
@@ -71,11 +300,13 @@ impl IoErrorExt for io::Error {
 }
 
 pub(crate) trait IoResultExt<T> {
+    #[cfg(feature = "fs")]
     fn with_file(self, file: PathBuf) -> Result<T>;
     fn with_offset(self, file: Option<PathBuf>, offset: Option<usize>) -> Result<T>;
 }
 
 impl<T> IoResultExt<T> for io::Result<T> {
+    #[cfg(feature = "fs")]
     fn with_file(self, file: PathBuf) -> Result<T> {
         self.map_err(|e| e.with_file(file))
     }
@@ -0,0 +1,217 @@
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::SystemTime,
+};
+
+use crate::{error::BigFileError, normalize_path, sanitize_path};
+
+// What to do when a file that's about to be extracted already exists
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Overwrite {
+    #[default]
+    Always,
+    // Existing files are skipped
+    Never,
+    // Existing files are only replaced if the bfdata was modified after them. In-memory
+    // bigfiles have no modification time, so nothing existing is replaced for those.
+    IfNewer,
+}
+
+type Filter = Arc<dyn Fn(&Path) -> bool + Send + Sync>;
+type ProgressFn = Arc<dyn Fn(usize, usize) + Send + Sync>;
+
+/// Everything that changes how `BigFile::extract_with` extracts, e.g.
+/// `ExtractOptions::new().output("out").overwrite(Overwrite::Never).threads(4)`
+#[derive(Clone, Default)]
+pub struct ExtractOptions {
+    pub(crate) output: PathBuf,
+    pub(crate) filter: Option<Filter>,
+    pub(crate) flatten: bool,
+    pub(crate) overwrite: Overwrite,
+    pub(crate) resume: bool,
+    pub(crate) threads: usize,
+    pub(crate) lossy: bool,
+    // Keyed by normalized path
+    pub(crate) mtimes: HashMap<String, SystemTime>,
+    pub(crate) default_mtime: Option<SystemTime>,
+    pub(crate) progress: Option<ProgressFn>,
+    pub(crate) cancel: Option<Arc<AtomicBool>>,
+    // Left out of every output path in any casing, see `BigFile::extract_paths`
+    pub(crate) prefix: PathBuf,
+    // Output file names by archive path when flattening, see `flat_names`
+    pub(crate) flat_names: HashMap<PathBuf, PathBuf>,
+}
+
+impl ExtractOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The directory to extract into, the current directory by default.
+    pub fn output(mut self, output: impl Into<PathBuf>) -> Self {
+        self.output = output.into();
+        self
+    }
+
+    /// Only extracts the entries whose archive path (e.g. `./textures/logo.dds`) passes the filter.
+    pub fn filter(mut self, filter: impl Fn(&Path) -> bool + Send + Sync + 'static) -> Self {
+        self.filter = Some(Arc::new(filter));
+        self
+    }
+
+    /// Writes every file straight into the output directory, dropping the directories
+    /// it's in. Files with the same name are numbered in path order, `logo.dds`,
+    /// `logo (2).dds` and so on.
+    pub fn flatten(mut self, flatten: bool) -> Self {
+        self.flatten = flatten;
+        self
+    }
+
+    pub fn overwrite(mut self, overwrite: Overwrite) -> Self {
+        self.overwrite = overwrite;
+        self
+    }
+
+    /// Leaves files that already exist with the entry's size alone, so that an interrupted
+    /// extraction can be run again and only writes what's missing. Checked before `overwrite`.
+    pub fn resume(mut self, resume: bool) -> Self {
+        self.resume = resume;
+        self
+    }
+
+    /// Splits the entries between this many threads. 0 and 1 both extract on the calling thread.
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = threads;
+        self
+    }
+
+    /// Keeps going when an entry fails to extract, recording it in the report,
+    /// instead of stopping at the first error.
+    pub fn lossy(mut self, lossy: bool) -> Self {
+        self.lossy = lossy;
+        self
+    }
+
+    /// Sets the modification times of extracted files, keyed by archive path in any casing
+    /// and with either slash. Bigfiles don't store any times of their own, so without this
+    /// every extracted file gets the time it was written at.
+    pub fn mtimes(mut self, mtimes: HashMap<PathBuf, SystemTime>) -> Self {
+        self.mtimes = mtimes
+            .into_iter()
+            .map(|(path, time)| (normalize_path(&path), time))
+            .collect();
+        self
+    }
+
+    /// The modification time for files that aren't in `mtimes`.
+    pub fn default_mtime(mut self, time: SystemTime) -> Self {
+        self.default_mtime = Some(time);
+        self
+    }
+
+    /// Called after each entry with the number of entries done so far and the total,
+    /// from whichever thread extracted it. Skipped and failed entries count as done.
+    pub fn progress(mut self, progress: impl Fn(usize, usize) + Send + Sync + 'static) -> Self {
+        self.progress = Some(Arc::new(progress));
+        self
+    }
+
+    /// Stops extracting once the flag is set, before starting on the next entry.
+    /// Files that were already written are kept, and the report is marked as cancelled.
+    pub fn cancel(mut self, cancel: Arc<AtomicBool>) -> Self {
+        self.cancel = Some(cancel);
+        self
+    }
+
+    pub(crate) fn is_cancelled(&self) -> bool {
+        self.cancel
+            .as_ref()
+            .is_some_and(|cancel| cancel.load(Ordering::Relaxed))
+    }
+
+    pub(crate) fn mtime_for(&self, path: &Path) -> Option<SystemTime> {
+        if self.mtimes.is_empty() {
+            return self.default_mtime;
+        }
+
+        self.mtimes
+            .get(&normalize_path(path))
+            .copied()
+            .or(self.default_mtime)
+    }
+}
+
+// The names `paths` are extracted under when flattening, by archive path. Names are
+// compared in lowercase, as they'd collide on case-insensitive file systems.
+pub(crate) fn flat_names<'a>(
+    paths: impl IntoIterator<Item = &'a PathBuf>,
+) -> HashMap<PathBuf, PathBuf> {
+    let mut paths: Vec<_> = paths.into_iter().collect();
+    paths.sort();
+
+    let mut taken = HashSet::new();
+    let mut names = HashMap::with_capacity(paths.len());
+    for path in paths {
+        // Paths that can't be extracted fail later on, when they're extracted
+        let Some(mut name) = sanitize_path(path)
+            .ok()
+            .and_then(|relative| Some(relative.file_name()?.to_string_lossy().into_owned()))
+        else {
+            continue;
+        };
+
+        if !taken.insert(name.to_lowercase()) {
+            let relative = Path::new(&name);
+            let stem = relative.file_stem().unwrap_or_default().to_string_lossy();
+            let extension = match relative.extension() {
+                Some(ext) => format!(".{}", ext.to_string_lossy()),
+                None => String::new(),
+            };
+
+            name = (2..)
+                .map(|n| format!("{stem} ({n}){extension}"))
+                .find(|name| taken.insert(name.to_lowercase()))
+                .unwrap();
+        }
+        names.insert(path.clone(), PathBuf::from(name));
+    }
+
+    names
+}
+
+/// What `BigFile::extract_with` did.
+#[derive(Debug, Default)]
+pub struct ExtractReport {
+    /// Output paths of the files that were written
+    pub written: Vec<PathBuf>,
+    /// Output paths that already existed and were kept, see `Overwrite`
+    pub skipped: Vec<PathBuf>,
+    /// How many files were already extracted in full, see `ExtractOptions::resume`
+    pub complete: usize,
+    /// Archive paths that couldn't be extracted, only filled in when extracting lossily
+    pub failed: Vec<(PathBuf, BigFileError)>,
+    /// Whether the extraction was stopped early, see `ExtractOptions::cancel`
+    pub cancelled: bool,
+}
+
+impl ExtractReport {
+    pub(crate) fn merge(&mut self, other: ExtractReport) {
+        self.written.extend(other.written);
+        self.skipped.extend(other.skipped);
+        self.complete += other.complete;
+        self.failed.extend(other.failed);
+        self.cancelled |= other.cancelled;
+    }
+}
+
+// What happened to a single entry
+pub(crate) enum Outcome {
+    Written(PathBuf),
+    Skipped(PathBuf),
+    Complete,
+}
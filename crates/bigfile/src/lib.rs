@@ -1,75 +1,389 @@
-mod bfdb;
-mod bfn;
+pub mod bfdb;
+pub mod bfn;
+#[cfg(feature = "fs")]
+mod cache;
+mod crc32;
+mod cursor;
+mod diff;
 pub mod error;
+#[cfg(feature = "fs")]
+mod extract;
+#[cfg(feature = "fs")]
+mod options;
 mod reader;
+mod stats;
+mod writer;
+#[cfg(feature = "zip")]
+mod zip_export;
 
 use std::{
-    collections::HashMap,
-    fs,
-    io::{Cursor, Read, Seek, SeekFrom},
-    path::PathBuf,
+    collections::{HashMap, HashSet},
+    fmt,
+    io::{self, Cursor, Read, Seek, SeekFrom, Write},
+    ops::Range,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, MutexGuard},
+};
+#[cfg(feature = "fs")]
+use std::{
+    fs::{self, File, OpenOptions},
+    io::BufReader,
+    path::Component,
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
+    time::SystemTime,
 };
 
-pub use crate::error::Result;
 use crate::{
     bfdb::Bfdb,
     bfn::Bfn,
-    error::{BigFileError, IoResultExt},
+    crc32::Crc32,
+    cursor::CursorSource,
+    error::{BigFileError, IoErrorExt, IoResultExt},
     reader::BigFileReader,
 };
+pub use crate::{
+    cursor::EntryCursor,
+    diff::{Change, Diff},
+    error::Result,
+    stats::{Stats, extension},
+    writer::BigFileWriter,
+};
+#[cfg(feature = "fs")]
+use crate::{
+    extract::Outcome,
+    reader::{SharedFile, read_file_at},
+};
+#[cfg(feature = "fs")]
+pub use crate::{
+    extract::{ExtractOptions, ExtractReport, Overwrite},
+    options::BigFileOptions,
+};
+pub use bigfile_core::{DEFAULT_MAX_DEPTH, DEFAULT_ROOT_STRIP, Endianness, Part, sniff};
+
+// See `bigfile_core::entry_hash` and `BigFileOptions::root_strip`
+fn entry_hash(path: &Path, root_strip: usize) -> Result<u64> {
+    let Some(path) = path.to_str() else {
+        return Err(BigFileError::NonUtf8Path(path.to_path_buf()));
+    };
+    Ok(bigfile_core::entry_hash(path, root_strip))
+}
+
+// Puts a path into the form the archive hashes: lowercase, with forward slashes
+// and without the root directory, so `./Textures\UI\Logo.DDS` becomes `textures/ui/logo.dds`
+pub fn normalize_path(path: &Path) -> String {
+    let path = path.to_string_lossy().replace('\\', "/").to_lowercase();
+    let parts: Vec<_> = path
+        .split('/')
+        .filter(|p| !p.is_empty() && *p != ".")
+        .collect();
+    parts.join("/")
+}
+
+// The hash the bfdb would store for a path, see `normalize_path`. Only right for bigfiles
+// with the default root strip, `BigFile::hash_of` works for any of them.
+pub fn hash_path(path: &Path) -> u64 {
+    bigfile_core::fnv1a(&normalize_path(path))
+}
+
+// Entry paths come straight from the bfn, so they can't be trusted to stay
+// inside the output directory. Both kinds of slashes count as separators,
+// and absolute paths are refused along with `..` and drive letters.
+pub fn sanitize_path(path: &Path) -> Result<PathBuf> {
+    let mut sanitized = PathBuf::new();
+
+    let lossy = path.to_string_lossy();
+    if lossy.starts_with(['/', '\\']) {
+        return Err(BigFileError::UnsafePath(path.to_path_buf()));
+    }
+
+    for part in lossy.split(['/', '\\']) {
+        match part {
+            "" | "." => {}
+            ".." => return Err(BigFileError::UnsafePath(path.to_path_buf())),
+            part if part.contains(':') => return Err(BigFileError::UnsafePath(path.to_path_buf())),
+            part => sanitized.push(part),
+        }
+    }
+
+    if sanitized.as_os_str().is_empty() {
+        return Err(BigFileError::UnsafePath(path.to_path_buf()));
+    }
+
+    Ok(sanitized)
+}
+
+// The deepest directory all the paths are in, so `a/b/c.txt` and `a/b/d/e.txt` give `a/b`
+pub fn common_prefix<'a>(paths: impl IntoIterator<Item = &'a Path>) -> PathBuf {
+    let mut paths = paths.into_iter();
+    let Some(first) = paths.next() else {
+        return PathBuf::new();
+    };
+
+    let mut prefix = first.parent().unwrap_or(Path::new("")).to_path_buf();
+    for path in paths {
+        let parent = path.parent().unwrap_or(Path::new(""));
+        while !parent.starts_with(&prefix) && prefix.pop() {}
+    }
+
+    prefix
+}
+
+// Whether `target` should be written given the overwrite policy and when the bfdata was modified
+#[cfg(feature = "fs")]
+fn should_write(target: &Path, overwrite: Overwrite, modified: Option<SystemTime>) -> Result<bool> {
+    if overwrite == Overwrite::Always {
+        return Ok(true);
+    }
+
+    let existing = match fs::metadata(target) {
+        Ok(metadata) => metadata,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(true),
+        Err(err) => return Err(err.with_file(target.to_path_buf())),
+    };
+
+    match (overwrite, modified) {
+        (Overwrite::IfNewer, Some(modified)) => {
+            let existing = existing.modified().with_file(target.to_path_buf())?;
+            Ok(existing < modified)
+        }
+        _ => Ok(false),
+    }
+}
+
+// `relative` without `prefix`, if it starts with it in any casing. Both should already
+// be sanitized, see `sanitize_path`.
+#[cfg(feature = "fs")]
+fn strip_prefix_ignore_case<'a>(relative: &'a Path, prefix: &Path) -> &'a Path {
+    let lowercase = |part: Component| part.as_os_str().to_string_lossy().to_lowercase();
+
+    let mut rest = relative.components();
+    for part in prefix.components() {
+        match rest.next() {
+            Some(c) if lowercase(c) == lowercase(part) => {}
+            _ => return relative,
+        }
+    }
+
+    match rest.as_path() {
+        // Never strips the whole path, so there's always a file name left
+        rest if rest.as_os_str().is_empty() => relative,
+        rest => rest,
+    }
+}
+
+// Whether `target` was already extracted, going by its size
+#[cfg(feature = "fs")]
+fn is_complete(target: &Path, size: u64) -> bool {
+    fs::metadata(target).is_ok_and(|metadata| metadata.is_file() && metadata.len() == size)
+}
 
-fn fnv1a(string: &str) -> u64 {
-    let mut hash: u64 = 0xCBF29CE484222325;
-    for char in string.chars() {
-        hash ^= char as u64;
-        hash = hash.wrapping_mul(0x100000001B3);
+// Guesses which part of a bigfile the file at `path` is from its contents rather than
+// its extension, for parts that were renamed. See `sniff`.
+#[cfg(feature = "fs")]
+pub fn sniff_file(path: &Path) -> Result<Part> {
+    let file = File::open(path).with_file(path.to_path_buf())?;
+    let len = file.metadata().with_file(path.to_path_buf())?.len();
+
+    let mut head = Vec::with_capacity(bigfile_core::SNIFF_LEN);
+    file.take(bigfile_core::SNIFF_LEN as u64)
+        .read_to_end(&mut head)
+        .with_file(path.to_path_buf())?;
+
+    Ok(sniff(&head, len))
+}
+
+#[cfg(feature = "fs")]
+fn sibling_path(bfn_path: &Path, extension: &str) -> Result<PathBuf> {
+    let path = bfn_path.with_extension(extension);
+
+    match fs::exists(&path) {
+        Ok(true) => Ok(path),
+        Ok(false) => Err(BigFileError::SiblingNotFound(path)),
+        Err(err) => Err(err.with_file(path)),
     }
-    hash
 }
 
+// Reported while parsing. The number of names isn't stored anywhere in the bfn,
+// so only the amount read so far is known for it.
+#[derive(Clone, Copy, Debug)]
+pub enum Progress {
+    Names { read: u64 },
+    Entries { read: u64, total: u64 },
+}
+
+#[derive(Clone, Copy, Debug)]
 pub struct Entry {
     offset: u64,
     size: u64,
 }
 
+/// A range of the bfdata that no entry uses, see `BigFile::layout_report`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Gap {
+    pub start: u64,
+    pub end: u64,
+}
+
+impl Gap {
+    pub fn len(&self) -> u64 {
+        self.end - self.start
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+}
+
+impl Entry {
+    // Where the entry's data starts in the bfdata
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+}
+
+// What errors call the parts of a bigfile that aren't read from a path
+pub(crate) const BFN_LABEL: &str = "<bfn>";
+pub(crate) const BFDB_LABEL: &str = "<bfdb>";
+pub(crate) const BFDATA_LABEL: &str = "<bfdata>";
+
+// How much of an entry is read at once when streaming it
+const COPY_CHUNK_LEN: usize = 64 * 1024;
+
+// Three u64 section offsets, see `BigFile::from_combined`
+#[cfg(feature = "fs")]
+const COMBINED_HEADER_LEN: u64 = 24;
+
+/// What `BigFile::verify_all` found.
+#[derive(Debug, Default)]
+pub struct VerifyReport {
+    /// How many entries could be read in full
+    pub verified: usize,
+    pub failed: Vec<(PathBuf, BigFileError)>,
+}
+
+/// Anything the bfdata can be read from with `DataSource::Dynamic`. It's implemented
+/// for every `Read + Seek` type that can be sent between threads.
+pub trait ReadSeek: Read + Seek + Send {}
+
+impl<T: Read + Seek + Send> ReadSeek for T {}
+
 pub enum DataSource {
+    #[cfg(feature = "fs")]
     File(PathBuf),
-    Buffer(Cursor<Vec<u8>>),
+    // Shared, so that reads don't have to copy the whole buffer
+    Buffer(Arc<[u8]>),
+    // Any other reader, e.g. one that decrypts or decompresses the bfdata as it's read.
+    // Reads take turns on it, as they have to seek it first.
+    Dynamic(Box<dyn ReadSeek>),
 }
 
-pub struct BigFile {
-    entries: HashMap<PathBuf, Entry>,
-    bfdata: DataSource,
+// The bfdata as it's held on to. Files are opened once and read with positional reads,
+// so that any number of threads can read from the same handle at once. Entry cursors
+// share the handle too, so a bigfile never has more than the one open.
+enum Bfdata {
+    #[cfg(feature = "fs")]
+    File {
+        path: PathBuf,
+        file: Arc<File>,
+    },
+    Buffer(Arc<[u8]>),
+    Dynamic(Mutex<Box<dyn ReadSeek>>),
 }
 
-impl BigFile {
-    pub fn entries(&self) -> &HashMap<PathBuf, Entry> {
-        &self.entries
+impl Bfdata {
+    fn open(source: DataSource) -> Result<Self> {
+        match source {
+            #[cfg(feature = "fs")]
+            DataSource::File(path) => {
+                let file = File::open(&path).with_file(path.clone())?;
+                Ok(Bfdata::File {
+                    path,
+                    file: Arc::new(file),
+                })
+            }
+            DataSource::Buffer(buf) => Ok(Bfdata::Buffer(buf)),
+            DataSource::Dynamic(reader) => Ok(Bfdata::Dynamic(Mutex::new(reader))),
+        }
     }
+}
 
-    pub fn from_paths(bfn_path: PathBuf, bfdb_path: PathBuf, bfdata: DataSource) -> Result<Self> {
-        let mut reader = BigFileReader::from_path(bfn_path)?;
-        let bfn = Bfn::from(&mut reader)?;
+// Every read seeks before it reads, so a reader left mid-read by a panic is still usable
+fn lock(reader: &Mutex<Box<dyn ReadSeek>>) -> MutexGuard<'_, Box<dyn ReadSeek>> {
+    reader
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
 
-        let mut reader = BigFileReader::from_path(bfdb_path)?;
-        let bfdb = Bfdb::from(&mut reader)?;
+pub struct BigFile {
+    entries: HashMap<PathBuf, Entry>,
+    // Entry paths by their hash, for lookups with differently written paths
+    hashes: HashMap<u64, PathBuf>,
+    // Bfdb hashes that no bfn path hashes to, in bfdb order
+    orphans: Vec<u64>,
+    // Every bfdb record as it was read, so that `patch` keeps their order and the orphans
+    records: Vec<(u64, Entry)>,
+    // The bfn's root directory, which every entry path starts with
+    root: PathBuf,
+    bfdata: Bfdata,
+    // Where the bfdata starts in its source, only non-zero for combined files
+    data_offset: u64,
+    // See `DEFAULT_ROOT_STRIP`
+    root_strip: usize,
+    // Of the bfdb, so that `patch` writes a new one the same way
+    endianness: Endianness,
+    // Only for bigfiles opened from paths, see `reload`
+    #[cfg(feature = "fs")]
+    origin: Option<Origin>,
+}
 
-        BigFile::from(bfn, bfdb, bfdata)
-    }
+// Where a bigfile was opened from and how, so that it can be opened again the same way
+#[cfg(feature = "fs")]
+struct Origin {
+    bfn: PathBuf,
+    bfdb: PathBuf,
+    // Only known for bfdatas read from a path, either as a file or into memory
+    bfdata: Option<PathBuf>,
+    options: BigFileOptions,
+}
 
-    fn from(bfn: Bfn, bfdb: Bfdb, bfdata: DataSource) -> Result<Self> {
-        let mut entries = HashMap::with_capacity(bfn.files.len());
+// What's worked out from the bfn and bfdb, see `BigFile` for the fields
+struct Index {
+    entries: HashMap<PathBuf, Entry>,
+    hashes: HashMap<u64, PathBuf>,
+    orphans: Vec<u64>,
+    records: Vec<(u64, Entry)>,
+    root: PathBuf,
+}
+
+impl Index {
+    fn build(bfn: Bfn, bfdb: Bfdb, collect_missing: bool, root_strip: usize) -> Result<Self> {
+        let total = bfn.files.len();
+        let mut missing = Vec::new();
+
+        let mut entries = HashMap::with_capacity(total);
+        let mut hashes = HashMap::with_capacity(total);
         for path in bfn.files {
-            // The path passed to the hashing function should be lowercase,
-            // should replace all backslashes with normal slashes,
-            // and should not include the root directory (hence the [2..])
-            let hash = fnv1a(&path.to_str().unwrap().replace('\\', "/").to_lowercase()[2..]);
+            let hash = entry_hash(&path, root_strip)?;
 
             let entry = match bfdb.entries.get(&hash) {
                 Some(v) => v,
-                None => return Err(BigFileError::HashEntryNotFound(hash)),
+                None if collect_missing => {
+                    log::warn!("{} (hash {hash:X}) has no bfdb entry", path.display());
+                    missing.push(path);
+                    continue;
+                }
+                None => {
+                    log::warn!("{} (hash {hash:X}) has no bfdb entry", path.display());
+                    return Err(BigFileError::HashEntryNotFound(hash));
+                }
             };
 
+            hashes.insert(hash, path.clone());
             entries.insert(
                 path,
                 Entry {
@@ -79,7 +393,262 @@ impl BigFile {
             );
         }
 
-        Ok(BigFile { entries, bfdata })
+        if !missing.is_empty() {
+            missing.sort();
+            return Err(BigFileError::MissingEntries { missing, total });
+        }
+
+        let mut seen = HashSet::new();
+        let orphans: Vec<u64> = bfdb
+            .records
+            .iter()
+            .map(|(hash, _)| *hash)
+            .filter(|hash| !hashes.contains_key(hash) && seen.insert(*hash))
+            .collect();
+
+        if !orphans.is_empty() {
+            let list: Vec<_> = orphans.iter().map(|hash| format!("{hash:016X}")).collect();
+            log::warn!(
+                "{} bfdb entries have no bfn path: {}",
+                orphans.len(),
+                list.join(", ")
+            );
+        }
+
+        let records = bfdb
+            .records
+            .into_iter()
+            .map(|(hash, entry)| {
+                let entry = Entry {
+                    offset: entry.offset,
+                    size: entry.size,
+                };
+                (hash, entry)
+            })
+            .collect();
+
+        Ok(Index {
+            entries,
+            hashes,
+            orphans,
+            records,
+            root: PathBuf::from(bfn.root.name),
+        })
+    }
+}
+
+impl BigFile {
+    pub fn entries(&self) -> &HashMap<PathBuf, Entry> {
+        &self.entries
+    }
+
+    // Whether the path is in the archive, looked up like `find` does. Nothing is read.
+    pub fn contains(&self, file: impl AsRef<Path>) -> bool {
+        self.find(file).is_some()
+    }
+
+    // Same as `contains`, reads better next to `try_get`
+    pub fn has(&self, file: impl AsRef<Path>) -> bool {
+        self.contains(file)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    // In no particular order
+    pub fn paths(&self) -> impl Iterator<Item = &PathBuf> {
+        self.entries.keys()
+    }
+
+    // Hashes of the bfdb entries that no bfn path hashes to, in bfdb order. Their data is
+    // in the bfdata, but it can't be looked up without knowing the path.
+    pub fn orphan_hashes(&self) -> Vec<u64> {
+        self.orphans.clone()
+    }
+
+    // Opens a bigfile from its bfn, expecting the bfdb and bfdata next to it with the same name
+    #[cfg(feature = "fs")]
+    pub fn open(bfn_path: PathBuf) -> Result<Self> {
+        let bfdb_path = sibling_path(&bfn_path, "bfdb")?;
+        let bfdata_path = sibling_path(&bfn_path, "bfdata")?;
+
+        BigFile::from_paths(bfn_path, bfdb_path, DataSource::File(bfdata_path))
+    }
+
+    #[cfg(feature = "fs")]
+    pub fn from_paths(bfn_path: PathBuf, bfdb_path: PathBuf, bfdata: DataSource) -> Result<Self> {
+        BigFile::from_paths_with_progress(bfn_path, bfdb_path, bfdata, |_| {})
+    }
+
+    #[cfg(feature = "fs")]
+    pub fn from_paths_with_progress(
+        bfn_path: PathBuf,
+        bfdb_path: PathBuf,
+        bfdata: DataSource,
+        progress: impl FnMut(Progress),
+    ) -> Result<Self> {
+        BigFile::from_paths_inner(
+            bfn_path,
+            bfdb_path,
+            bfdata,
+            &BigFileOptions::new(),
+            progress,
+        )
+    }
+
+    // Like `from_paths`, but instead of failing on the first bfn path without a bfdb entry,
+    // goes through all of them and fails with `MissingEntries` listing every one
+    #[cfg(feature = "fs")]
+    pub fn from_paths_collect_missing(
+        bfn_path: PathBuf,
+        bfdb_path: PathBuf,
+        bfdata: DataSource,
+    ) -> Result<Self> {
+        let options = BigFileOptions::new().collect_missing(true);
+        BigFile::from_paths_inner(bfn_path, bfdb_path, bfdata, &options, |_| {})
+    }
+
+    // Like `from_paths`, for bigfiles whose bfn paths don't start with a single-character
+    // root directory. `root_strip` is how many characters are left out of each path's hash,
+    // see `DEFAULT_ROOT_STRIP`.
+    #[cfg(feature = "fs")]
+    pub fn from_paths_with_root_strip(
+        bfn_path: PathBuf,
+        bfdb_path: PathBuf,
+        bfdata: DataSource,
+        root_strip: usize,
+    ) -> Result<Self> {
+        let options = BigFileOptions::new().root_strip(root_strip);
+        BigFile::from_paths_inner(bfn_path, bfdb_path, bfdata, &options, |_| {})
+    }
+
+    #[cfg(feature = "fs")]
+    fn from_paths_inner(
+        bfn_path: PathBuf,
+        bfdb_path: PathBuf,
+        bfdata: DataSource,
+        options: &BigFileOptions,
+        mut progress: impl FnMut(Progress),
+    ) -> Result<Self> {
+        // Worked out before reading, so that changes made while reading make the cache stale
+        let cache = options.index_cache.as_ref().and_then(|path| {
+            let key = cache::key(&bfn_path, &bfdb_path, options)?;
+            Some((path, key))
+        });
+
+        let index = match cache.and_then(|(path, key)| cache::load(path, &key)) {
+            Some(index) => index,
+            None => {
+                let mut reader = BigFileReader::from_path(bfn_path.clone())?
+                    .lossy_strings(options.lossy_strings)
+                    .endianness(options.endianness)
+                    .max_depth(options.max_depth);
+                let bfn = Bfn::from(&mut reader, &mut progress)?;
+
+                let mut reader =
+                    BigFileReader::from_path(bfdb_path.clone())?.endianness(options.endianness);
+                let bfdb = Bfdb::from(&mut reader, &mut progress)?;
+
+                let index = Index::build(bfn, bfdb, options.collect_missing, options.root_strip)?;
+                if let Some((path, key)) = cache
+                    && let Err(err) = cache::store(path, &key, &index)
+                {
+                    log::warn!("couldn't write the index cache {}: {err}", path.display());
+                }
+                index
+            }
+        };
+
+        let bfdata_path = match &bfdata {
+            DataSource::File(path) => Some(path.clone()),
+            _ => None,
+        };
+
+        let mut bigfile = BigFile::from_index(index, bfdata, options.root_strip)?;
+        bigfile.endianness = options.endianness;
+        bigfile.origin = Some(Origin {
+            bfn: bfn_path,
+            bfdb: bfdb_path,
+            bfdata: bfdata_path,
+            options: options.clone(),
+        });
+        Ok(bigfile)
+    }
+
+    fn from(
+        bfn: Bfn,
+        bfdb: Bfdb,
+        bfdata: DataSource,
+        collect_missing: bool,
+        root_strip: usize,
+    ) -> Result<Self> {
+        let index = Index::build(bfn, bfdb, collect_missing, root_strip)?;
+        BigFile::from_index(index, bfdata, root_strip)
+    }
+
+    fn from_index(index: Index, bfdata: DataSource, root_strip: usize) -> Result<Self> {
+        Ok(BigFile {
+            entries: index.entries,
+            hashes: index.hashes,
+            orphans: index.orphans,
+            records: index.records,
+            root: index.root,
+            bfdata: Bfdata::open(bfdata)?,
+            data_offset: 0,
+            root_strip,
+            endianness: Endianness::Little,
+            #[cfg(feature = "fs")]
+            origin: None,
+        })
+    }
+
+    // Opens a bigfile that has all three parts in one file. It starts with a header of
+    // three little-endian u64s giving where the bfn, bfdb and bfdata start, in that order.
+    // Each part ends where the next one starts, and the bfdata runs to the end of the file.
+    #[cfg(feature = "fs")]
+    pub fn from_combined(path: PathBuf) -> Result<Self> {
+        let mut reader = BigFileReader::from_path(path.clone())?;
+        let bfn_start = reader.read_u64_le()?;
+        let bfdb_start = reader.read_u64_le()?;
+        let bfdata_start = reader.read_u64_le()?;
+        let len = reader.len()?;
+
+        let in_order = COMBINED_HEADER_LEN <= bfn_start
+            && bfn_start <= bfdb_start
+            && bfdb_start <= bfdata_start
+            && bfdata_start <= len;
+        if !in_order {
+            return Err(reader.invalid_data(format!(
+                "the bfn, bfdb and bfdata offsets {bfn_start}, {bfdb_start} and {bfdata_start} \
+                don't fit in order in a file of {len} bytes"
+            )));
+        }
+
+        let file = Arc::new(File::open(&path).with_file(path.clone())?);
+        // Offsets in errors are from the start of the section, which the label says
+        let section = |start: u64, end: u64, label: &str| -> Result<_> {
+            let shared = BufReader::new(SharedFile::new(Arc::clone(&file)));
+            let cursor = EntryCursor::new(CursorSource::File(shared), start, end - start)
+                .with_offset(Some(path.clone()), Some(start as _))?;
+            let label = format!("{} {label}", path.display());
+            Ok(BigFileReader::new(cursor).label(label))
+        };
+
+        let bfn = Bfn::from(&mut section(bfn_start, bfdb_start, BFN_LABEL)?, &mut |_| {})?;
+        let bfdb = Bfdb::from(
+            &mut section(bfdb_start, bfdata_start, BFDB_LABEL)?,
+            &mut |_| {},
+        )?;
+
+        let mut bigfile =
+            BigFile::from(bfn, bfdb, DataSource::File(path), false, DEFAULT_ROOT_STRIP)?;
+        bigfile.data_offset = bfdata_start;
+        Ok(bigfile)
     }
 
     pub fn new<R: Read + Seek>(
@@ -87,119 +656,942 @@ impl BigFile {
         bfdb_reader: &mut R,
         bfdata_reader: &mut R,
     ) -> Result<Self> {
-        let mut bfn = BigFileReader::new(bfn_reader);
-        let mut bfdb = BigFileReader::new(bfdb_reader);
-        let mut bfdata = BigFileReader::new(bfdata_reader);
+        let mut bfn = BigFileReader::new(bfn_reader).label(BFN_LABEL);
+        let mut bfdb = BigFileReader::new(bfdb_reader).label(BFDB_LABEL);
+        let mut bfdata = BigFileReader::new(bfdata_reader).label(BFDATA_LABEL);
 
         let mut buf = Vec::new();
         bfdata.read_to_end(&mut buf)?;
-        let cursor = Cursor::new(buf);
 
         BigFile::from(
-            Bfn::from(&mut bfn)?,
-            Bfdb::from(&mut bfdb)?,
-            DataSource::Buffer(cursor),
+            Bfn::from(&mut bfn, &mut |_| {})?,
+            Bfdb::from(&mut bfdb, &mut |_| {})?,
+            DataSource::Buffer(buf.into()),
+            false,
+            DEFAULT_ROOT_STRIP,
         )
     }
 
-    pub fn get(&self, file: &PathBuf) -> Result<Vec<u8>> {
-        let entry = match self.entries.get(file) {
-            Some(v) => v,
-            None => return Err(BigFileError::EntryNotFound(file.clone())),
-        };
+    // Finds the path an entry is stored under. The path is looked up as given first,
+    // and then normalized like the archive does when hashing, so that it doesn't have to
+    // match the bfn's casing or slashes. `Textures\UI\Logo.DDS` finds `./textures/ui/logo.dds`.
+    pub fn find(&self, file: impl AsRef<Path>) -> Option<&PathBuf> {
+        let file = file.as_ref();
+        match self.entries.get_key_value(file) {
+            Some((path, _)) => Some(path),
+            None => self.hashes.get(&self.hash_of(file)),
+        }
+    }
 
-        let mut data = vec![0; entry.size as _];
+    // The hash the bfdb has for a path, normalized like `find` does. Unlike `hash_path`,
+    // it's hashed under this bigfile's root and with its root strip.
+    pub fn hash_of(&self, file: impl AsRef<Path>) -> u64 {
+        let path = self.root.join(self.normalize_relative(file.as_ref()));
+        bigfile_core::entry_hash(&path.to_string_lossy(), self.root_strip)
+    }
 
-        match &self.bfdata {
-            DataSource::File(path_buf) => {
-                let mut reader = BigFileReader::from_path(path_buf.clone())?;
+    // `normalize_path`, without this bigfile's root if the path starts with it,
+    // so that paths can be given either way
+    fn normalize_relative(&self, path: &Path) -> String {
+        let path = normalize_path(path);
+        let root = normalize_path(&self.root);
+        if root.is_empty() {
+            return path;
+        }
 
-                reader.seek(SeekFrom::Start(entry.offset))?;
-                reader.read_exact(&mut data)?;
+        match path.strip_prefix(&root) {
+            Some("") => String::new(),
+            Some(rest) if rest.starts_with('/') => rest[1..].to_string(),
+            _ => path,
+        }
+    }
+
+    // Finds the candidate paths that hash to any of `hashes`, e.g. to put names to
+    // `orphan_hashes` from a wordlist. Candidates are hashed like `hash_of` does,
+    // and the first one found for each hash is kept.
+    pub fn resolve_hashes(
+        &self,
+        hashes: &[u64],
+        candidates: impl Iterator<Item = String>,
+    ) -> HashMap<u64, String> {
+        let wanted: HashSet<u64> = hashes.iter().copied().collect();
+        let mut resolved = HashMap::new();
+
+        for candidate in candidates {
+            if resolved.len() == wanted.len() {
+                break;
             }
-            DataSource::Buffer(cursor) => {
-                let mut reader = BigFileReader::new(cursor.clone());
 
-                reader.seek(SeekFrom::Start(entry.offset))?;
-                reader.read_exact(&mut data)?;
+            let hash = self.hash_of(&candidate);
+            if wanted.contains(&hash) {
+                resolved.entry(hash).or_insert(candidate);
             }
-        };
+        }
+
+        resolved
+    }
 
+    fn entry(&self, file: &Path) -> Result<&Entry> {
+        // Exact paths are by far the most common, so they skip hashing and the second lookup
+        if let Some(entry) = self.entries.get(file) {
+            return Ok(entry);
+        }
+
+        match self
+            .hashes
+            .get(&self.hash_of(file))
+            .and_then(|path| self.entries.get(path))
+        {
+            Some(v) => Ok(v),
+            None => Err(BigFileError::EntryNotFound(file.to_path_buf())),
+        }
+    }
+
+    // Where the entry's data is in the bfdata file, for reading it some other way.
+    // For combined files, it's where it is in the combined file.
+    pub fn entry_range(&self, file: impl AsRef<Path>) -> Result<Range<u64>> {
+        let entry = self.entry(file.as_ref())?;
+        let start = self.data_offset + entry.offset;
+        Ok(start..start + entry.size)
+    }
+
+    // Accepts any casing and slashes, see `find`. Takes anything path-like,
+    // so `bigfile.get("textures/logo.dds")` works as is.
+    pub fn get(&self, file: impl AsRef<Path>) -> Result<Vec<u8>> {
+        let file = file.as_ref();
+        let entry = self.entry(file)?;
+        self.read_entry(file, entry)
+    }
+
+    // Like `get`, but a path that isn't in the archive is `Ok(None)` rather than an error,
+    // so that only failed reads are errors
+    pub fn try_get(&self, file: impl AsRef<Path>) -> Result<Option<Vec<u8>>> {
+        let file = file.as_ref();
+        match self.find(file) {
+            Some(path) => self.read_entry(path, &self.entries[path]).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    // Like `get`, but only ever looks the path up normalized
+    pub fn get_normalized(&self, file: impl AsRef<Path>) -> Result<Vec<u8>> {
+        let file = file.as_ref();
+        match self
+            .hashes
+            .get(&self.hash_of(file))
+            .and_then(|path| self.entries.get_key_value(path))
+        {
+            Some((path, entry)) => self.read_entry(path, entry),
+            None => Err(BigFileError::EntryNotFound(file.to_path_buf())),
+        }
+    }
+
+    fn read_entry(&self, path: &Path, entry: &Entry) -> Result<Vec<u8>> {
+        // A corrupt bfdb could claim any size, which would be allocated before the read fails
+        if entry.size > self.bfdata_len()?.saturating_sub(entry.offset) {
+            let eof = io::Error::from(io::ErrorKind::UnexpectedEof).into();
+            return Err(self.truncated(path, entry, eof));
+        }
+
+        let mut data = vec![0; entry.size as _];
+        self.read_at(entry.offset, &mut data)
+            .map_err(|err| self.truncated(path, entry, err))?;
         Ok(data)
     }
 
-    pub fn extract(&self, output_path: PathBuf) -> Result<()> {
+    // Turns a short read of the entry into `Truncated`, passes other errors through
+    fn truncated(&self, path: &Path, entry: &Entry, err: BigFileError) -> BigFileError {
+        if err.io_kind() != Some(io::ErrorKind::UnexpectedEof) {
+            return err;
+        }
+
+        let Ok(len) = self.bfdata_len() else {
+            return err;
+        };
+
+        BigFileError::Truncated {
+            path: path.to_path_buf(),
+            offset: entry.offset,
+            wanted: entry.size,
+            available: len.saturating_sub(entry.offset),
+        }
+    }
+
+    // Length of the bfdata itself, not counting anything before it in a combined file
+    fn bfdata_len(&self) -> Result<u64> {
+        let len = match &self.bfdata {
+            #[cfg(feature = "fs")]
+            Bfdata::File { path, file } => file.metadata().with_file(path.clone())?.len(),
+            Bfdata::Buffer(buf) => buf.len() as u64,
+            Bfdata::Dynamic(reader) => lock(reader).seek(SeekFrom::End(0))?,
+        };
+
+        Ok(len.saturating_sub(self.data_offset))
+    }
+
+    // Fills `buf` with the bfdata at `offset`, without moving any shared cursor
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> Result<()> {
+        // Zero-size entries can point anywhere, even past the end of the bfdata
+        if buf.is_empty() {
+            return Ok(());
+        }
+
+        let offset = self.data_offset + offset;
+
+        match &self.bfdata {
+            #[cfg(feature = "fs")]
+            Bfdata::File { path, file } => {
+                read_file_at(file, buf, offset).with_offset(Some(path.clone()), Some(offset as _))
+            }
+            Bfdata::Buffer(data) => {
+                let start = offset as usize;
+                match data.get(start..start.saturating_add(buf.len())) {
+                    Some(v) => {
+                        buf.copy_from_slice(v);
+                        Ok(())
+                    }
+                    None => Err(io::Error::from(io::ErrorKind::UnexpectedEof)
+                        .with_offset(Some(BFDATA_LABEL.into()), Some(start))),
+                }
+            }
+            Bfdata::Dynamic(reader) => {
+                let mut reader = lock(reader);
+                reader
+                    .seek(SeekFrom::Start(offset))
+                    .and_then(|_| reader.read_exact(buf))
+                    .with_offset(Some(BFDATA_LABEL.into()), Some(offset as _))
+            }
+        }
+    }
+
+    // Copies the entry's data to `out` a chunk at a time
+    fn copy_entry(&self, path: &Path, entry: &Entry, out: &mut impl Write) -> Result<()> {
+        let mut buf = vec![0; COPY_CHUNK_LEN.min(entry.size as usize)];
+        let mut copied = 0;
+
+        while copied < entry.size {
+            let len = (entry.size - copied).min(buf.len() as u64) as usize;
+            self.read_at(entry.offset + copied, &mut buf[..len])
+                .map_err(|err| self.truncated(path, entry, err))?;
+            out.write_all(&buf[..len])?;
+            copied += len as u64;
+        }
+
+        Ok(())
+    }
+
+    // Streams the entry's data into `out` without buffering all of it,
+    // returns the number of bytes written
+    pub fn extract_entry_to<W: Write>(&self, file: impl AsRef<Path>, mut out: W) -> Result<u64> {
+        let file = file.as_ref();
+        let entry = self.entry(file)?;
+
+        self.copy_entry(file, entry, &mut out)?;
+        out.flush()?;
+
+        Ok(entry.size)
+    }
+
+    // CRC-32 of the entry's data, the same checksum zip and gzip use. The data is streamed
+    // through it rather than read into memory.
+    pub fn entry_crc32(&self, file: impl AsRef<Path>) -> Result<u32> {
+        let mut crc = Crc32::new();
+        self.extract_entry_to(file, &mut crc)?;
+        Ok(crc.finish())
+    }
+
+    // Reads every entry through to the end without keeping any of it, to catch truncated
+    // or mismatched bfdatas. Failures are collected instead of stopping at the first one.
+    pub fn verify_all(&self) -> Result<VerifyReport> {
+        let mut entries: Vec<_> = self.entries.iter().collect();
+        // In bfdata order, so that reads go front to back
+        entries.sort_by_key(|(path, entry)| (entry.offset, *path));
+
+        let mut report = VerifyReport::default();
+        for (path, entry) in entries {
+            match self.copy_entry(path, entry, &mut io::sink()) {
+                Ok(()) => report.verified += 1,
+                Err(err) => {
+                    log::debug!("failed to verify {}: {err}", path.display());
+                    report.failed.push((path.clone(), err));
+                }
+            }
+        }
+
+        Ok(report)
+    }
+
+    // The CRC-32 of every entry, see `entry_crc32`
+    pub fn checksums(&self) -> Result<HashMap<PathBuf, u32>> {
+        let mut checksums = HashMap::with_capacity(self.entries.len());
+
+        for (path, entry) in &self.entries {
+            let mut crc = Crc32::new();
+            self.copy_entry(path, entry, &mut crc)?;
+            checksums.insert(path.clone(), crc.finish());
+        }
+
+        Ok(checksums)
+    }
+
+    // Opens the entry as its own `Read + Seek` stream, for decoders that expect a whole file
+    pub fn entry_cursor(&self, file: impl AsRef<Path>) -> Result<EntryCursor> {
+        let file = file.as_ref();
+        let entry = self.entry(file)?;
+
         match &self.bfdata {
-            DataSource::File(path_buf) => {
-                let mut reader = BigFileReader::from_path(path_buf.clone())?;
-                return self.extract_inner(output_path, &mut reader);
+            // Keeps its own position, so it doesn't get in the way of other reads
+            #[cfg(feature = "fs")]
+            Bfdata::File {
+                path: path_buf,
+                file,
+            } => {
+                let shared = BufReader::new(SharedFile::new(Arc::clone(file)));
+                let start = self.data_offset + entry.offset;
+                EntryCursor::new(CursorSource::File(shared), start, entry.size)
+                    .with_offset(Some(path_buf.clone()), Some(start as _))
+            }
+            Bfdata::Buffer(buf) => {
+                let start = self.data_offset + entry.offset;
+                let cursor = Cursor::new(Arc::clone(buf));
+                Ok(EntryCursor::new(
+                    CursorSource::Buffer(cursor),
+                    start,
+                    entry.size,
+                )?)
+            }
+            // The reader can't be shared with the cursor, so the entry is read into memory
+            Bfdata::Dynamic(_) => {
+                let data = self.read_entry(file, entry)?;
+                let cursor = Cursor::new(Arc::from(data));
+                Ok(EntryCursor::new(
+                    CursorSource::Buffer(cursor),
+                    0,
+                    entry.size,
+                )?)
+            }
+        }
+    }
+
+    // Overwrites the entry's data at its current offset, padding it with zeros
+    // if the new data is smaller. Only the in-memory entry size is updated,
+    // the bfdb on disk still holds the old size.
+    pub fn replace_in_place(&mut self, file: &Path, new_data: &[u8]) -> Result<()> {
+        let path = match self.find(file) {
+            Some(path) => path.clone(),
+            None => return Err(BigFileError::EntryNotFound(file.to_path_buf())),
+        };
+        let entry = match self.entries.get_mut(&path) {
+            Some(v) => v,
+            None => return Err(BigFileError::EntryNotFound(path)),
+        };
+
+        if new_data.len() as u64 > entry.size {
+            return Err(BigFileError::ReplacementTooLarge {
+                file: path,
+                size: new_data.len() as _,
+                available: entry.size,
+            });
+        }
+
+        let mut data = new_data.to_vec();
+        data.resize(entry.size as _, 0);
+
+        match &mut self.bfdata {
+            #[cfg(feature = "fs")]
+            Bfdata::File { path: path_buf, .. } => {
+                let start = self.data_offset + entry.offset;
+                let offset = Some(start as _);
+                let mut bfdata = OpenOptions::new()
+                    .write(true)
+                    .open(&path_buf)
+                    .with_file(path_buf.clone())?;
+
+                bfdata
+                    .seek(SeekFrom::Start(start))
+                    .with_offset(Some(path_buf.clone()), offset)?;
+                bfdata
+                    .write_all(&data)
+                    .with_offset(Some(path_buf.clone()), offset)?;
+            }
+            Bfdata::Buffer(buf) => {
+                let start = (self.data_offset + entry.offset) as usize;
+                // Copies the buffer if anything else still holds on to it
+                match Arc::make_mut(buf).get_mut(start..start + data.len()) {
+                    Some(v) => v.copy_from_slice(&data),
+                    None => {
+                        return Err(io::Error::from(io::ErrorKind::UnexpectedEof)
+                            .with_offset(Some(BFDATA_LABEL.into()), Some(start)));
+                    }
+                }
             }
-            DataSource::Buffer(cursor) => {
-                let mut reader = BigFileReader::new(cursor.clone());
-                return self.extract_inner(output_path, &mut reader);
+            Bfdata::Dynamic(_) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "a dynamic data source can only be read from",
+                )
+                .into());
             }
         };
+
+        entry.size = new_data.len() as _;
+
+        // Keeps `patch` and `compact` in step with the new size
+        let hash = entry_hash(&path, self.root_strip)?;
+        for (_, record) in self.records.iter_mut().filter(|(other, _)| *other == hash) {
+            record.size = entry.size;
+        }
+
+        Ok(())
     }
 
-    fn extract_inner(
+    // Writes a copy of the bfdata with the given entries replaced, along with a bfdb
+    // pointing at the new data. Replacements that fit are written over the old data,
+    // larger ones and ones whose data other entries use too are appended to the end.
+    // The bfn doesn't change.
+    pub fn patch<W: Write>(
         &self,
-        output_path: PathBuf,
-        reader: &mut BigFileReader<impl Read + Seek>,
+        replacements: HashMap<PathBuf, Vec<u8>>,
+        bfdata_out: &mut W,
+        bfdb_out: &mut W,
     ) -> Result<()> {
-        for (path, entry) in &self.entries {
-            self.extract_entry(reader, &output_path, &path, &entry)?;
+        // Replacements are matched to bfdb records by their hashes
+        let mut hashed = HashMap::with_capacity(replacements.len());
+        for (path, data) in replacements {
+            match self.find(&path) {
+                Some(found) => hashed.insert(entry_hash(found, self.root_strip)?, data),
+                None => return Err(BigFileError::EntryNotFound(path)),
+            };
         }
+        let replacements = hashed;
 
-        Ok(())
+        match &self.bfdata {
+            #[cfg(feature = "fs")]
+            Bfdata::File { path, .. } => {
+                let mut reader = BigFileReader::from_path(path.clone())?;
+                self.patch_inner(&mut reader, replacements, bfdata_out, bfdb_out)
+            }
+            Bfdata::Buffer(buf) => {
+                let mut reader =
+                    BigFileReader::new(Cursor::new(Arc::clone(buf))).label(BFDATA_LABEL);
+                self.patch_inner(&mut reader, replacements, bfdata_out, bfdb_out)
+            }
+            Bfdata::Dynamic(reader) => {
+                let mut reader = lock(reader);
+                let mut reader = BigFileReader::new(&mut *reader).label(BFDATA_LABEL);
+                self.patch_inner(&mut reader, replacements, bfdata_out, bfdb_out)
+            }
+        }
     }
 
-    fn extract_entry(
+    fn patch_inner<W: Write>(
         &self,
         reader: &mut BigFileReader<impl Read + Seek>,
-        output_path: &PathBuf,
-        path: &PathBuf,
-        entry: &Entry,
+        replacements: HashMap<u64, Vec<u8>>,
+        bfdata_out: &mut W,
+        bfdb_out: &mut W,
     ) -> Result<()> {
-        let mut data = vec![0; entry.size as _];
+        let shared = self.shared_hashes();
+        let mut records = self.records.clone();
+
+        // Indices of the records being replaced, by where their data is
+        let mut replaced: Vec<usize> = (0..records.len())
+            .filter(|&i| replacements.contains_key(&records[i].0))
+            .collect();
+        replaced.sort_by_key(|&i| (records[i].1.offset, i));
+
+        let mut pos = 0;
+        let mut appended = Vec::new();
+        reader.seek(SeekFrom::Start(self.data_offset))?;
+
+        for i in replaced {
+            let (hash, entry) = &mut records[i];
+            let data = &replacements[hash];
 
-        reader.seek(SeekFrom::Start(entry.offset))?;
-        reader.read_exact(&mut data)?;
+            // Entries that are too large, or that share their data with another record or an
+            // already replaced entry, are appended after the original data instead
+            if data.len() as u64 > entry.size || entry.offset < pos || shared.contains(hash) {
+                appended.push(i);
+                continue;
+            }
+
+            reader.copy_exact(bfdata_out, entry.offset - pos)?;
+            bfdata_out.write_all(data)?;
+            bfdata_out.write_all(&vec![0; (entry.size - data.len() as u64) as _])?;
 
-        let path = std::env::current_dir()?.join(output_path).join(path);
+            pos = entry.offset + entry.size;
+            reader.seek(SeekFrom::Start(self.data_offset + pos))?;
+
+            entry.size = data.len() as _;
+        }
 
-        fs::create_dir_all(path.parent().unwrap())?;
-        fs::write(&path, data).with_file(path)?;
+        let mut end = pos + reader.copy_to_end(bfdata_out)?;
+
+        for i in appended {
+            let (hash, entry) = &mut records[i];
+            let data = &replacements[hash];
+            bfdata_out.write_all(data)?;
+
+            entry.offset = end;
+            entry.size = data.len() as _;
+            end += data.len() as u64;
+        }
+
+        self.write_records(&records, bfdb_out)?;
+        bfdata_out.flush()?;
 
         Ok(())
     }
 
-    pub fn extract_lossy(&self, output_path: PathBuf) -> Result<usize> {
-        match &self.bfdata {
-            DataSource::File(path_buf) => {
-                let mut reader = BigFileReader::from_path(path_buf.clone())?;
-                return Ok(self.extract_lossy_inner(output_path, &mut reader));
+    // Hashes of the records whose data overlaps that of a record with another hash, so that
+    // replacing one of them in place would change the others too
+    fn shared_hashes(&self) -> HashSet<u64> {
+        let mut ranges: Vec<(u64, u64, u64)> = self
+            .records
+            .iter()
+            .filter(|(_, entry)| entry.size > 0)
+            .map(|(hash, entry)| (entry.offset, entry.offset + entry.size, *hash))
+            .collect();
+        ranges.sort_unstable();
+
+        // Runs of ranges that each overlap an earlier one in the run. Every range in a run
+        // overlaps at least one other, so it's shared as soon as the run has two hashes.
+        let mut shared = HashSet::new();
+        let mut run: Vec<u64> = Vec::new();
+        let mut run_end = 0;
+        for (start, end, hash) in ranges {
+            if start >= run_end {
+                if run.iter().any(|other| *other != run[0]) {
+                    shared.extend(run.drain(..));
+                }
+                run.clear();
             }
-            DataSource::Buffer(cursor) => {
-                let mut reader = BigFileReader::new(cursor.clone());
-                return Ok(self.extract_lossy_inner(output_path, &mut reader));
+
+            run.push(hash);
+            run_end = run_end.max(end);
+        }
+        if run.iter().any(|other| *other != run[0]) {
+            shared.extend(run);
+        }
+
+        shared
+    }
+
+    // Writes bfdb records as they are, in the given order
+    fn write_records(&self, records: &[(u64, Entry)], out: &mut impl Write) -> Result<()> {
+        let order = self.endianness;
+        out.write_all(&order.u32_bytes(records.len() as u32))?;
+        for (hash, entry) in records {
+            out.write_all(&order.u64_bytes(entry.size))?;
+            out.write_all(&order.u64_bytes(entry.offset))?;
+            out.write_all(&order.u64_bytes(*hash))?;
+        }
+
+        out.flush()?;
+        Ok(())
+    }
+
+    // The parts of the bfdata that no bfdb record uses, in order. These build up when entries
+    // are replaced with smaller data or appended to the end by `patch`.
+    pub fn layout_report(&self) -> Result<Vec<Gap>> {
+        let mut records: Vec<_> = self.records.iter().map(|(_, entry)| entry).collect();
+        records.sort_by_key(|entry| entry.offset);
+
+        let mut gaps = Vec::new();
+        let mut pos = 0;
+        for entry in records {
+            if entry.offset > pos {
+                gaps.push(Gap {
+                    start: pos,
+                    end: entry.offset,
+                });
             }
-        };
+            pos = pos.max(entry.offset + entry.size);
+        }
+
+        let len = self.bfdata_len()?;
+        if len > pos {
+            gaps.push(Gap {
+                start: pos,
+                end: len,
+            });
+        }
+
+        Ok(gaps)
     }
 
-    fn extract_lossy_inner(
+    // Writes a copy of the bfdata without any gaps, see `layout_report`, along with a bfdb
+    // pointing at the new data. Records that shared their data still share it afterwards,
+    // and orphans keep theirs.
+    pub fn compact<W: Write>(&self, bfdata_out: &mut W, bfdb_out: &mut W) -> Result<()> {
+        let mut order: Vec<usize> = (0..self.records.len()).collect();
+        order.sort_by_key(|&i| {
+            let entry = &self.records[i].1;
+            (entry.offset, entry.size, i)
+        });
+
+        let mut compacted = self.records.clone();
+        let mut pos = 0;
+        // The old and new entry of the last data written
+        let mut previous: Option<(&Entry, Entry)> = None;
+
+        for i in order {
+            let (hash, entry) = &self.records[i];
+            let new = match previous {
+                Some((old, new)) if old.offset == entry.offset && old.size == entry.size => new,
+                _ => {
+                    let path = match self.hashes.get(hash) {
+                        Some(path) => path.clone(),
+                        None => PathBuf::from(format!("{hash:016X}")),
+                    };
+                    self.copy_entry(&path, entry, bfdata_out)?;
+                    let new = Entry {
+                        offset: pos,
+                        size: entry.size,
+                    };
+                    pos += entry.size;
+                    new
+                }
+            };
+
+            previous = Some((entry, new));
+            compacted[i].1 = new;
+        }
+
+        self.write_records(&compacted, bfdb_out)?;
+        bfdata_out.flush()?;
+
+        Ok(())
+    }
+}
+
+// How many of the largest entries `{:#}` lists
+const SUMMARY_LARGEST: usize = 5;
+
+// `1234 entries, 567.0 MB`. The alternate form `{:#}` also lists the largest entries.
+impl fmt::Display for BigFile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let total: u64 = self.entries.values().map(|e| e.size).sum();
+        write!(f, "{} entries, {}", self.entries.len(), format_size(total))?;
+
+        if f.alternate() {
+            let mut largest: Vec<_> = self.entries.iter().collect();
+            // By path as well, so that entries of the same size always come out in the same order
+            largest.sort_by(|a, b| b.1.size.cmp(&a.1.size).then_with(|| a.0.cmp(b.0)));
+
+            for (path, entry) in largest.iter().take(SUMMARY_LARGEST) {
+                write!(f, "\n{}\t{}", format_size(entry.size), path.display())?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// Sizes as they're shown to people, e.g. `512 B` or `1.5 MB`
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{size:.1} {}", UNITS[unit])
+    }
+}
+
+#[cfg(feature = "fs")]
+impl BigFile {
+    pub fn extract(&self, output_path: PathBuf) -> Result<()> {
+        self.extract_with_progress(output_path, |_, _| {})
+    }
+
+    // `progress` is called with the number of extracted files and the total after each file
+    pub fn extract_with_progress(
         &self,
         output_path: PathBuf,
-        reader: &mut BigFileReader<impl Read + Seek>,
-    ) -> usize {
-        let mut extracted = 0;
+        mut progress: impl FnMut(usize, usize),
+    ) -> Result<()> {
+        let options = ExtractOptions::new().output(output_path);
+        let entries: Vec<_> = self.entries.iter().collect();
+        self.extract_entries(&entries, &options, &mut progress)?;
+        Ok(())
+    }
+
+    pub fn extract_with(&self, options: &ExtractOptions) -> Result<ExtractReport> {
+        let entries: Vec<_> = match &options.filter {
+            Some(filter) => self.entries.iter().filter(|(p, _)| filter(p)).collect(),
+            None => self.entries.iter().collect(),
+        };
 
+        // Named up front, as same-named entries could end up on different threads
+        let flattened;
+        let options = if options.flatten {
+            flattened = ExtractOptions {
+                flat_names: extract::flat_names(entries.iter().map(|(path, _)| *path)),
+                ..options.clone()
+            };
+            &flattened
+        } else {
+            options
+        };
+
+        // Counted across all threads, rather than per chunk
+        let total = entries.len();
+        let done = AtomicUsize::new(0);
+        let progress = |_, _| {
+            let done = done.fetch_add(1, Ordering::Relaxed) + 1;
+            if let Some(progress) = &options.progress {
+                progress(done, total);
+            }
+        };
+
+        let threads = options.threads.clamp(1, entries.len().max(1));
+        if threads == 1 {
+            return self.extract_entries(&entries, options, &mut { progress });
+        }
+
+        // Reads don't share a cursor, so the threads can all use this bigfile as is
+        thread::scope(|scope| {
+            let handles: Vec<_> = entries
+                .chunks(entries.len().div_ceil(threads))
+                .map(|chunk| {
+                    scope.spawn(move || self.extract_entries(chunk, options, &mut { progress }))
+                })
+                .collect();
+
+            let mut report = ExtractReport::default();
+            for handle in handles {
+                match handle.join() {
+                    Ok(part) => report.merge(part?),
+                    Err(panic) => std::panic::resume_unwind(panic),
+                }
+            }
+
+            Ok(report)
+        })
+    }
+
+    fn extract_entries(
+        &self,
+        entries: &[(&PathBuf, &Entry)],
+        options: &ExtractOptions,
+        progress: &mut impl FnMut(usize, usize),
+    ) -> Result<ExtractReport> {
+        let modified = self.bfdata_modified();
+        let mut report = ExtractReport::default();
+
+        for (i, (path, entry)) in entries.iter().enumerate() {
+            if options.is_cancelled() {
+                log::debug!(
+                    "extraction cancelled after {i} of {} entries",
+                    entries.len()
+                );
+                report.cancelled = true;
+                break;
+            }
+
+            match self.extract_one(path, entry, options, modified) {
+                Ok(Outcome::Written(target)) => report.written.push(target),
+                Ok(Outcome::Skipped(target)) => report.skipped.push(target),
+                Ok(Outcome::Complete) => report.complete += 1,
+                Err(err) if options.lossy => {
+                    log::debug!("failed to extract {}: {err}", path.display());
+                    report.failed.push((path.to_path_buf(), err))
+                }
+                Err(err) => return Err(err),
+            }
+
+            progress(i + 1, entries.len());
+        }
+
+        Ok(report)
+    }
+
+    fn extract_one(
+        &self,
+        path: &Path,
+        entry: &Entry,
+        options: &ExtractOptions,
+        modified: Option<SystemTime>,
+    ) -> Result<Outcome> {
+        let relative = sanitize_path(path)?;
+        let relative = strip_prefix_ignore_case(&relative, &options.prefix);
+        let target = match options.flat_names.get(path) {
+            Some(name) if options.flatten => options.output.join(name),
+            _ => options.output.join(relative),
+        };
+
+        if options.resume && is_complete(&target, entry.size) {
+            log::debug!("skipped {}, it's already extracted", target.display());
+            return Ok(Outcome::Complete);
+        }
+
+        if !should_write(&target, options.overwrite, modified)? {
+            log::debug!("skipped {}, it already exists", target.display());
+            return Ok(Outcome::Skipped(target));
+        }
+
+        self.extract_entry(path, entry, &target)?;
+        log::trace!("extracted {} to {}", path.display(), target.display());
+
+        if let Some(mtime) = options.mtime_for(path) {
+            File::options()
+                .write(true)
+                .open(&target)
+                .and_then(|file| file.set_modified(mtime))
+                .with_file(target.clone())?;
+        }
+
+        Ok(Outcome::Written(target))
+    }
+
+    // Reads the bfn and bfdb again from the paths the bigfile was opened with, to pick up
+    // changes made to them since. A bfdata file is opened again, and one that was read
+    // into memory from a path is read again. Other bfdatas are kept as they are.
+    pub fn reload(&mut self) -> Result<()> {
+        let Some(origin) = &self.origin else {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "only bigfiles opened from paths can be reloaded",
+            )
+            .into());
+        };
+
+        let bfdata = match (&self.bfdata, &origin.bfdata) {
+            (Bfdata::File { path, .. }, _) => Some(DataSource::File(path.clone())),
+            (Bfdata::Buffer(_), Some(path)) => {
+                let buf = fs::read(path).with_file(path.clone())?;
+                Some(DataSource::Buffer(buf.into()))
+            }
+            _ => None,
+        };
+        let keep_bfdata = bfdata.is_none();
+
+        let reloaded = BigFile::from_paths_inner(
+            origin.bfn.clone(),
+            origin.bfdb.clone(),
+            bfdata.unwrap_or(DataSource::Buffer(Arc::new([]))),
+            &origin.options,
+            |_| {},
+        )?;
+
+        let previous = std::mem::replace(self, reloaded);
+        self.origin = previous.origin;
+        if keep_bfdata {
+            self.bfdata = previous.bfdata;
+        }
+
+        Ok(())
+    }
+
+    fn bfdata_modified(&self) -> Option<SystemTime> {
+        match &self.bfdata {
+            Bfdata::File { file, .. } => file.metadata().and_then(|m| m.modified()).ok(),
+            Bfdata::Buffer(_) | Bfdata::Dynamic(_) => None,
+        }
+    }
+
+    // Every file `extract` would write and its size, sorted by path, without touching the disk
+    pub fn extract_plan(&self, output_path: PathBuf) -> Result<Vec<(PathBuf, u64)>> {
+        let mut plan = Vec::with_capacity(self.entries.len());
         for (path, entry) in &self.entries {
-            let extracted_entry = self.extract_entry(reader, &output_path, &path, &entry);
-            if extracted_entry.is_ok() {
-                extracted += 1
+            plan.push((output_path.join(sanitize_path(path)?), entry.size));
+        }
+
+        plan.sort();
+        Ok(plan)
+    }
+
+    fn extract_entry(&self, path: &Path, entry: &Entry, target: &Path) -> Result<()> {
+        let data = self.read_entry(path, entry)?;
+
+        fs::create_dir_all(target.parent().unwrap())?;
+        fs::write(target, data).with_file(target.to_path_buf())?;
+
+        Ok(())
+    }
+
+    // Extracts only the given paths, which are looked up like `find` does. With
+    // `strip_common_prefix`, the directories they're all in are left out of the output,
+    // so `./textures/ui/a.dds` and `./textures/ui/b/c.dds` end up at `a.dds` and `b/c.dds`.
+    pub fn extract_paths(
+        &self,
+        paths: &[PathBuf],
+        output_path: PathBuf,
+        strip_common_prefix: bool,
+    ) -> Result<ExtractReport> {
+        let mut entries = Vec::with_capacity(paths.len());
+        for path in paths {
+            match self.find(path) {
+                Some(found) => entries.push((found, &self.entries[found])),
+                None => return Err(BigFileError::EntryNotFound(path.clone())),
             }
         }
 
-        extracted
+        let mut options = ExtractOptions::new().output(output_path);
+        if strip_common_prefix {
+            let prefix = common_prefix(entries.iter().map(|(path, _)| path.as_path()));
+            // Nothing to strip when it's only the root
+            options.prefix = sanitize_path(&prefix).unwrap_or_default();
+        }
+
+        self.extract_entries(&entries, &options, &mut |_, _| {})
+    }
+
+    // Extracts every entry in the `prefix` directory and the ones in it, e.g. `textures/ui`.
+    // The prefix is matched in any casing, with either slash and with or without the root,
+    // like `find` does.
+    // With `strip_prefix`, the entries are extracted relative to it rather than the root.
+    // Returns how many files were extracted.
+    pub fn extract_subtree(
+        &self,
+        prefix: &Path,
+        output_path: PathBuf,
+        strip_prefix: bool,
+    ) -> Result<usize> {
+        let prefix = self.normalize_relative(prefix);
+        let entries: Vec<_> = self
+            .entries
+            .iter()
+            .filter(|(path, _)| {
+                let path = self.normalize_relative(path);
+                prefix.is_empty()
+                    || path
+                        .strip_prefix(&prefix)
+                        .is_some_and(|rest| rest.starts_with('/'))
+            })
+            .collect();
+
+        let mut options = ExtractOptions::new().output(output_path);
+        if strip_prefix {
+            // Extracted paths keep the root unless it's `.`, so it's stripped along with the prefix
+            options.prefix = sanitize_path(&self.root.join(&prefix)).unwrap_or_default();
+        }
+
+        Ok(self
+            .extract_entries(&entries, &options, &mut |_, _| {})?
+            .written
+            .len())
+    }
+
+    // Extracts every entry, carrying on past the ones that fail, and reports which files
+    // were written and why the others weren't
+    pub fn extract_report(&self, output_path: PathBuf) -> Result<ExtractReport> {
+        let options = ExtractOptions::new().output(output_path).lossy(true);
+        let entries: Vec<_> = self.entries.iter().collect();
+
+        self.extract_entries(&entries, &options, &mut |_, _| {})
+    }
+
+    // Returns how many files were extracted
+    pub fn extract_lossy(&self, output_path: PathBuf) -> Result<usize> {
+        Ok(self.extract_report(output_path)?.written.len())
     }
 }
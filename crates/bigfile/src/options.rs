@@ -0,0 +1,112 @@
+use std::{fs, path::PathBuf};
+
+use crate::{
+    BigFile, DEFAULT_MAX_DEPTH, DEFAULT_ROOT_STRIP, DataSource, Endianness, Progress,
+    error::{IoResultExt, Result},
+};
+
+/// Everything that changes how a bigfile is opened, e.g.
+/// `BigFileOptions::new().in_memory(true).open(bfn, bfdb, bfdata)`
+#[derive(Clone, Debug)]
+pub struct BigFileOptions {
+    pub(crate) root_strip: usize,
+    pub(crate) lossy_strings: bool,
+    pub(crate) in_memory: bool,
+    pub(crate) collect_missing: bool,
+    pub(crate) endianness: Endianness,
+    pub(crate) max_depth: usize,
+    pub(crate) index_cache: Option<PathBuf>,
+}
+
+impl Default for BigFileOptions {
+    fn default() -> Self {
+        BigFileOptions {
+            root_strip: DEFAULT_ROOT_STRIP,
+            lossy_strings: false,
+            in_memory: false,
+            collect_missing: false,
+            endianness: Endianness::Little,
+            max_depth: DEFAULT_MAX_DEPTH,
+            index_cache: None,
+        }
+    }
+}
+
+impl BigFileOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// How many characters of each bfn path are left out of its hash, see `DEFAULT_ROOT_STRIP`.
+    pub fn root_strip(mut self, root_strip: usize) -> Self {
+        self.root_strip = root_strip;
+        self
+    }
+
+    /// Replaces invalid UTF-8 in bfn names with `�` instead of failing to open the bigfile.
+    pub fn lossy_strings(mut self, lossy: bool) -> Self {
+        self.lossy_strings = lossy;
+        self
+    }
+
+    /// Reads the whole bfdata into memory when opening, rather than reading entries
+    /// from the file as they're needed.
+    pub fn in_memory(mut self, in_memory: bool) -> Self {
+        self.in_memory = in_memory;
+        self
+    }
+
+    /// Goes through every bfn path before failing, so that the error lists all the paths
+    /// without a bfdb entry instead of only the first one. See `BigFileError::MissingEntries`.
+    pub fn collect_missing(mut self, collect: bool) -> Self {
+        self.collect_missing = collect;
+        self
+    }
+
+    /// How deeply directories can be nested in the bfn before it's rejected as corrupt,
+    /// `DEFAULT_MAX_DEPTH` by default.
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Keeps the paths, offsets and sizes read from the bfn and bfdb in this file, and reads
+    /// them from it instead the next time the same bfn and bfdb are opened with the same
+    /// options. The cache is written again whenever the bfn or bfdb changed size or
+    /// modification time since. Failing to write it is logged and otherwise ignored.
+    pub fn index_cache(mut self, path: impl Into<PathBuf>) -> Self {
+        self.index_cache = Some(path.into());
+        self
+    }
+
+    /// The byte order of the bfn and bfdb, little-endian by default.
+    pub fn endianness(mut self, endianness: Endianness) -> Self {
+        self.endianness = endianness;
+        self
+    }
+
+    pub fn open(&self, bfn: PathBuf, bfdb: PathBuf, bfdata: PathBuf) -> Result<BigFile> {
+        self.open_with_progress(bfn, bfdb, bfdata, |_| {})
+    }
+
+    pub fn open_with_progress(
+        &self,
+        bfn: PathBuf,
+        bfdb: PathBuf,
+        bfdata: PathBuf,
+        progress: impl FnMut(Progress),
+    ) -> Result<BigFile> {
+        if !self.in_memory {
+            return BigFile::from_paths_inner(bfn, bfdb, DataSource::File(bfdata), self, progress);
+        }
+
+        let buf = fs::read(&bfdata).with_file(bfdata.clone())?;
+        let mut bigfile =
+            BigFile::from_paths_inner(bfn, bfdb, DataSource::Buffer(buf.into()), self, progress)?;
+        // So that `BigFile::reload` reads it again
+        if let Some(origin) = &mut bigfile.origin {
+            origin.bfdata = Some(bfdata);
+        }
+        Ok(bigfile)
+    }
+}
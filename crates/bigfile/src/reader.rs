@@ -1,27 +1,128 @@
+#[cfg(feature = "fs")]
+use std::{fs::File, io::BufReader, sync::Arc};
 use std::{
-    fs::File,
-    io::{self, BufReader, Read, Seek, SeekFrom},
+    io::{self, Read, Seek, SeekFrom, Write},
     path::PathBuf,
 };
 
-use crate::error::{IoErrorExt, IoResultExt, Result};
+use bigfile_core::{DEFAULT_MAX_DEPTH, MAX_STRING_LEN, Source};
+
+use crate::{
+    Endianness,
+    error::{BigFileError, IoErrorExt, IoResultExt, Result},
+};
+
+// Reads up to `buf.len()` bytes at `offset` without going through the file's cursor,
+// so that several threads can share one handle
+#[cfg(all(feature = "fs", unix))]
+fn read_file_at_most(file: &File, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+    use std::os::unix::fs::FileExt;
+
+    file.read_at(buf, offset)
+}
+
+#[cfg(all(feature = "fs", windows))]
+fn read_file_at_most(file: &File, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+    use std::os::windows::fs::FileExt;
+
+    file.seek_read(buf, offset)
+}
+
+// Without positional reads, falls back to seeking the shared handle
+#[cfg(all(feature = "fs", not(any(unix, windows))))]
+fn read_file_at_most(mut file: &File, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+    file.seek(SeekFrom::Start(offset))?;
+    file.read(buf)
+}
+
+// Like `read_file_at_most`, but reads exactly `buf.len()` bytes
+#[cfg(feature = "fs")]
+pub(crate) fn read_file_at(file: &File, mut buf: &mut [u8], mut offset: u64) -> io::Result<()> {
+    while !buf.is_empty() {
+        match read_file_at_most(file, buf, offset) {
+            Ok(0) => return Err(io::Error::from(io::ErrorKind::UnexpectedEof)),
+            Ok(read) => {
+                buf = &mut buf[read..];
+                offset += read as u64;
+            }
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+
+    Ok(())
+}
+
+// A stream over a file handle that's shared with the bigfile, with a position of its own.
+// Streams don't need a handle each this way, however many of them are open.
+#[cfg(feature = "fs")]
+pub(crate) struct SharedFile {
+    file: Arc<File>,
+    pos: u64,
+}
+
+#[cfg(feature = "fs")]
+impl SharedFile {
+    pub(crate) fn new(file: Arc<File>) -> Self {
+        SharedFile { file, pos: 0 }
+    }
+}
+
+#[cfg(feature = "fs")]
+impl Read for SharedFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = read_file_at_most(&self.file, buf, self.pos)?;
+        self.pos += read as u64;
+        Ok(read)
+    }
+}
+
+#[cfg(feature = "fs")]
+impl Seek for SharedFile {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let target = match pos {
+            SeekFrom::Start(v) => Some(v),
+            SeekFrom::End(v) => self.file.metadata()?.len().checked_add_signed(v),
+            SeekFrom::Current(v) => self.pos.checked_add_signed(v),
+        };
+
+        match target {
+            Some(target) => {
+                self.pos = target;
+                Ok(target)
+            }
+            None => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "can't seek before the start of the file",
+            )),
+        }
+    }
+}
 
 pub(crate) struct BigFileReader<R: Read + Seek> {
     inner: R,
     file: Option<PathBuf>,
+    // Length of the whole stream, found on first use
+    len: Option<u64>,
+    // Whether invalid UTF-8 in strings is replaced instead of being an error
+    lossy_strings: bool,
+    endianness: Endianness,
+    // See `Source::max_depth`
+    max_depth: usize,
 }
 
 impl<R: Read + Seek> BigFileReader<R> {
     pub(crate) fn seek(&mut self, pos: SeekFrom) -> Result<u64> {
+        // The position the seek was aiming for, if it can be known without the stream's length
         let offset = match pos {
-            SeekFrom::Start(v) => v,
-            SeekFrom::End(v) => v as _,
-            SeekFrom::Current(v) => v as _,
+            SeekFrom::Start(v) => usize::try_from(v).ok(),
+            SeekFrom::End(_) => None,
+            SeekFrom::Current(v) => self
+                .pos()
+                .and_then(|pos| pos.checked_add_signed(v.try_into().ok()?)),
         };
 
-        self.inner
-            .seek(pos)
-            .with_offset(self.file.clone(), Some(offset as _))
+        self.inner.seek(pos).with_offset(self.file.clone(), offset)
     }
 
     pub(crate) fn read_exact(&mut self, buf: &mut [u8]) -> Result<()> {
@@ -38,13 +139,90 @@ impl<R: Read + Seek> BigFileReader<R> {
             .with_offset(self.file.clone(), offset)
     }
 
+    pub(crate) fn copy_exact(&mut self, writer: &mut impl Write, len: u64) -> Result<()> {
+        let offset = self.pos();
+        let copied = io::copy(&mut (&mut self.inner).take(len), writer)
+            .with_offset(self.file.clone(), offset)?;
+
+        if copied < len {
+            Err(io::Error::from(io::ErrorKind::UnexpectedEof)
+                .with_offset(self.file.clone(), offset))
+        } else {
+            Ok(())
+        }
+    }
+
+    pub(crate) fn copy_to_end(&mut self, writer: &mut impl Write) -> Result<u64> {
+        let offset = self.pos();
+        io::copy(&mut self.inner, writer).with_offset(self.file.clone(), offset)
+    }
+
     pub(crate) fn new(reader: R) -> Self {
         BigFileReader {
             inner: reader,
             file: None,
+            len: None,
+            lossy_strings: false,
+            endianness: Endianness::Little,
+            max_depth: DEFAULT_MAX_DEPTH,
         }
     }
 
+    // Names a reader that has no path in errors, e.g. `<bfn>` for an in-memory bfn
+    pub(crate) fn label(mut self, label: impl Into<PathBuf>) -> Self {
+        self.file = Some(label.into());
+        self
+    }
+
+    #[cfg(feature = "fs")]
+    pub(crate) fn lossy_strings(mut self, lossy: bool) -> Self {
+        self.lossy_strings = lossy;
+        self
+    }
+
+    pub(crate) fn endianness(mut self, endianness: Endianness) -> Self {
+        self.endianness = endianness;
+        self
+    }
+
+    #[cfg(feature = "fs")]
+    pub(crate) fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    // Length of the whole stream. Found by seeking to the end and back the first time,
+    // so the position is where it was before either way.
+    pub(crate) fn len(&mut self) -> Result<u64> {
+        if let Some(len) = self.len {
+            return Ok(len);
+        }
+
+        let pos = self.stream_position()?;
+        let len = self.seek(SeekFrom::End(0))?;
+        self.seek(SeekFrom::Start(pos))?;
+        Ok(*self.len.insert(len))
+    }
+
+    // Number of bytes left between the current position and the end of the stream
+    pub(crate) fn remaining(&mut self) -> Result<u64> {
+        let pos = self.stream_position()?;
+        Ok(self.len()?.saturating_sub(pos))
+    }
+
+    fn stream_position(&mut self) -> Result<u64> {
+        let offset = self.pos();
+        self.inner
+            .stream_position()
+            .with_offset(self.file.clone(), offset)
+    }
+
+    // An error for data that doesn't make sense at the current position
+    pub(crate) fn invalid_data(&mut self, message: String) -> BigFileError {
+        let offset = self.pos();
+        io::Error::new(io::ErrorKind::InvalidData, message).with_offset(self.file.clone(), offset)
+    }
+
     fn pos(&mut self) -> Option<usize> {
         if let Ok(pos) = self.inner.stream_position() {
             Some(pos as _)
@@ -53,40 +231,109 @@ impl<R: Read + Seek> BigFileReader<R> {
         }
     }
 
-    pub(crate) fn read_u32_le(&mut self) -> Result<u32> {
-        let mut buf = [0; 4];
+    pub(crate) fn read_exact_n<const N: usize>(&mut self) -> Result<[u8; N]> {
+        let mut buf = [0; N];
         self.read_exact(&mut buf)?;
-        Ok(u32::from_le_bytes(buf))
+        Ok(buf)
     }
 
+    // Only used for the header of combined files, see `BigFile::from_combined`
+    #[cfg(feature = "fs")]
     pub(crate) fn read_u64_le(&mut self) -> Result<u64> {
-        let mut buf = [0; 8];
-        self.read_exact(&mut buf)?;
-        Ok(u64::from_le_bytes(buf))
+        self.read_exact_n().map(u64::from_le_bytes)
+    }
+
+    // In the byte order set with `endianness`, little-endian by default
+    pub(crate) fn read_u32(&mut self) -> Result<u32> {
+        let bytes = self.read_exact_n()?;
+        Ok(match self.endianness {
+            Endianness::Little => u32::from_le_bytes(bytes),
+            Endianness::Big => u32::from_be_bytes(bytes),
+        })
+    }
+
+    pub(crate) fn read_u64(&mut self) -> Result<u64> {
+        let bytes = self.read_exact_n()?;
+        Ok(match self.endianness {
+            Endianness::Little => u64::from_le_bytes(bytes),
+            Endianness::Big => u64::from_be_bytes(bytes),
+        })
     }
 
     pub(crate) fn read_string(&mut self, len: usize) -> Result<String> {
+        // A corrupt length would otherwise allocate gigabytes before the read fails
+        if len > MAX_STRING_LEN {
+            return Err(self.invalid_data(format!(
+                "string length {len} is over the limit of {MAX_STRING_LEN} bytes"
+            )));
+        }
+
+        let remaining = self.remaining()?;
+        if len as u64 > remaining {
+            return Err(self.invalid_data(format!(
+                "string length {len} is more than the {remaining} bytes left"
+            )));
+        }
+
         let pos = self.pos();
         let mut buf = vec![0; len];
         self.read_exact(&mut buf)?;
 
-        if let Ok(string) = String::from_utf8(buf) {
-            Ok(string)
-        } else {
-            Err(
+        match String::from_utf8(buf) {
+            Ok(string) => Ok(string),
+            Err(err) if self.lossy_strings => {
+                let string = String::from_utf8_lossy(err.as_bytes()).into_owned();
+                log::warn!("replaced invalid UTF-8 in {string:?}");
+                Ok(string)
+            }
+            Err(_) => Err(
                 io::Error::new(io::ErrorKind::InvalidData, "read string was not UTF-8")
                     .with_offset(self.file.clone(), pos),
-            )
+            ),
         }
     }
 }
 
+// Lets the parsers in `bigfile_core` read through it
+impl<R: Read + Seek> Source for BigFileReader<R> {
+    type Error = BigFileError;
+
+    fn read_u32(&mut self) -> Result<u32> {
+        BigFileReader::read_u32(self)
+    }
+
+    fn read_u64(&mut self) -> Result<u64> {
+        BigFileReader::read_u64(self)
+    }
+
+    fn read_string(&mut self, len: usize) -> Result<String> {
+        BigFileReader::read_string(self, len)
+    }
+
+    fn remaining(&mut self) -> Result<u64> {
+        BigFileReader::remaining(self)
+    }
+
+    fn invalid_data(&mut self, message: String) -> BigFileError {
+        BigFileReader::invalid_data(self, message)
+    }
+
+    fn max_depth(&self) -> usize {
+        self.max_depth
+    }
+}
+
+#[cfg(feature = "fs")]
 impl BigFileReader<BufReader<File>> {
     pub(crate) fn from_path(path: PathBuf) -> Result<Self> {
         let inner = File::open(&path).with_file(path.clone())?;
         Ok(Self {
             inner: BufReader::new(inner),
             file: Some(path),
+            len: None,
+            lossy_strings: false,
+            endianness: Endianness::Little,
+            max_depth: DEFAULT_MAX_DEPTH,
         })
     }
 }
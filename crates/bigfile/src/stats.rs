@@ -0,0 +1,105 @@
+use std::{
+    cmp::Reverse,
+    collections::BTreeMap,
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use crate::{BigFile, format_size};
+
+/// An overview of a bigfile's entries, see `BigFile::stats`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Stats {
+    pub entries: usize,
+    pub total_size: u64,
+    pub mean_size: f64,
+    /// The average of the two middle sizes when there's an even number of entries
+    pub median_size: u64,
+    /// The largest entry and its size. Of entries with the same size, the first by path.
+    pub largest: Option<(PathBuf, u64)>,
+    /// How many entries have each extension, lowercase and without the dot.
+    /// Entries without an extension are counted under `""`.
+    pub by_extension: BTreeMap<String, usize>,
+}
+
+// One line per stat, with the extensions from most to least common
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Entries: {}", self.entries)?;
+        writeln!(f, "Total size: {}", format_size(self.total_size))?;
+        writeln!(f, "Mean size: {}", format_size(self.mean_size as u64))?;
+        write!(f, "Median size: {}", format_size(self.median_size))?;
+
+        if let Some((path, size)) = &self.largest {
+            write!(f, "\nLargest: {} ({})", path.display(), format_size(*size))?;
+        }
+
+        for (ext, count) in self.extensions_by_count() {
+            match ext {
+                "" => write!(f, "\nNo extension: {count}")?,
+                ext => write!(f, "\n.{ext}: {count}")?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Stats {
+    /// `by_extension` from the most to the least common extension,
+    /// and alphabetically for extensions that are as common.
+    pub fn extensions_by_count(&self) -> Vec<(&str, usize)> {
+        let mut extensions: Vec<_> = self
+            .by_extension
+            .iter()
+            .map(|(ext, count)| (ext.as_str(), *count))
+            .collect();
+        extensions.sort_by_key(|(_, count)| Reverse(*count));
+        extensions
+    }
+}
+
+impl BigFile {
+    /// Counts and sizes of the entries, without reading any of them.
+    pub fn stats(&self) -> Stats {
+        let mut sizes: Vec<_> = self.entries.values().map(|e| e.size).collect();
+        sizes.sort_unstable();
+
+        let total_size = sizes.iter().sum();
+        let median_size = match sizes.len() {
+            0 => 0,
+            len if len % 2 == 0 => sizes[len / 2 - 1].midpoint(sizes[len / 2]),
+            len => sizes[len / 2],
+        };
+
+        let largest = self
+            .entries
+            .iter()
+            .min_by_key(|(path, entry)| (Reverse(entry.size), *path))
+            .map(|(path, entry)| (path.clone(), entry.size));
+
+        let mut by_extension = BTreeMap::new();
+        for path in self.entries.keys() {
+            *by_extension.entry(extension(path)).or_default() += 1;
+        }
+
+        Stats {
+            entries: sizes.len(),
+            total_size,
+            mean_size: match sizes.len() {
+                0 => 0.0,
+                len => total_size as f64 / len as f64,
+            },
+            median_size,
+            largest,
+            by_extension,
+        }
+    }
+}
+
+/// A path's extension the way `Stats::by_extension` counts it.
+pub fn extension(path: &Path) -> String {
+    path.extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .unwrap_or_default()
+}
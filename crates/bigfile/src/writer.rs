@@ -0,0 +1,206 @@
+use std::{
+    collections::HashMap,
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    DEFAULT_ROOT_STRIP,
+    bfdb::Entry,
+    bfn::BfnDir,
+    entry_hash,
+    error::{BigFileError, Result},
+    normalize_path,
+};
+
+// Paths are hashed without their first `DEFAULT_ROOT_STRIP` characters,
+// so the root directory has to have a single-character name
+const ROOT: &str = ".";
+
+/// Builds a bigfile out of files. `new` keeps the bfdata in memory until `write`,
+/// `streaming` writes it out as files are added.
+pub struct BigFileWriter<D: Write = Vec<u8>> {
+    // In the order they were first added, with where their data is in `bfdata`
+    files: Vec<(PathBuf, Entry)>,
+    // Indices into `files` by `normalize_path`, which is what's hashed
+    indices: HashMap<String, usize>,
+    bfdata: D,
+    // Bytes written to `bfdata` so far
+    len: u64,
+    // Every file's data starts at a multiple of this
+    alignment: u64,
+}
+
+impl Default for BigFileWriter {
+    fn default() -> Self {
+        BigFileWriter::streaming(Vec::new())
+    }
+}
+
+impl BigFileWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a file to the archive. `archive_path` is relative to the archive root,
+    /// e.g. `textures/ui/logo.dds`. Adding the same path twice replaces its data,
+    /// the old data is still written to the bfdata but nothing points to it.
+    /// Fails without adding anything for paths that don't name a file, and for ones
+    /// that only differ in casing from one that's already in.
+    pub fn add_file(&mut self, archive_path: PathBuf, data: &[u8]) -> Result<()> {
+        let path = self.stored_path(&archive_path)?;
+        let offset = self.aligned_len();
+        self.bfdata.resize(offset as usize, 0);
+        self.len = offset;
+
+        self.bfdata.extend_from_slice(data);
+        self.len += data.len() as u64;
+        self.insert(path, offset, data.len() as u64);
+        Ok(())
+    }
+
+    pub fn write<W: Write>(
+        self,
+        bfn_out: &mut W,
+        bfdb_out: &mut W,
+        bfdata_out: &mut W,
+    ) -> Result<()> {
+        let bfdata = self.finish(bfn_out, bfdb_out)?;
+
+        bfdata_out.write_all(&bfdata)?;
+        bfdata_out.flush()?;
+
+        Ok(())
+    }
+}
+
+impl<D: Write> BigFileWriter<D> {
+    /// Writes the data of every added file straight to `bfdata`, so that archives bigger
+    /// than memory can be built. The bfn and bfdb are written by `finish` once every file
+    /// is in. A path that's added again leaves its old data in the bfdata, unused.
+    pub fn streaming(bfdata: D) -> Self {
+        BigFileWriter {
+            files: Vec::new(),
+            indices: HashMap::new(),
+            bfdata,
+            len: 0,
+            alignment: 1,
+        }
+    }
+
+    /// Starts the data of every file at a multiple of `alignment` bytes, e.g. 2048 for
+    /// engines that stream whole sectors, padding the gaps with zeros. 0 and 1 leave no gaps.
+    pub fn with_alignment(mut self, alignment: u64) -> Self {
+        self.alignment = alignment.max(1);
+        self
+    }
+
+    /// Copies `size` bytes from `reader` into the bfdata as a file, through a small buffer.
+    /// Fails if the reader ends before that. See `add_file` for `archive_path`.
+    pub fn add_file_from_reader(
+        &mut self,
+        archive_path: PathBuf,
+        reader: impl Read,
+        size: u64,
+    ) -> Result<()> {
+        let path = self.stored_path(&archive_path)?;
+        let offset = self.aligned_len();
+        io::copy(&mut io::repeat(0).take(offset - self.len), &mut self.bfdata)?;
+        self.len = offset;
+
+        let copied = io::copy(&mut reader.take(size), &mut self.bfdata)?;
+        self.len += copied;
+
+        if copied < size {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                format!(
+                    "{} ended after {copied} of {size} bytes",
+                    archive_path.display()
+                ),
+            )
+            .into());
+        }
+
+        self.insert(path, offset, size);
+        Ok(())
+    }
+
+    /// Writes the bfn and bfdb for every file added so far, flushing all three.
+    /// Returns the bfdata writer.
+    pub fn finish<W: Write>(mut self, bfn_out: &mut W, bfdb_out: &mut W) -> Result<D> {
+        let mut root = BfnDir::new(ROOT.to_string());
+
+        bfdb_out.write_all(&(self.files.len() as u32).to_le_bytes())?;
+        for (path, entry) in &self.files {
+            insert_path(&mut root, path);
+
+            bfdb_out.write_all(&entry.size.to_le_bytes())?;
+            bfdb_out.write_all(&entry.offset.to_le_bytes())?;
+            bfdb_out.write_all(&entry_hash(path, DEFAULT_ROOT_STRIP)?.to_le_bytes())?;
+        }
+
+        root.write(bfn_out)?;
+
+        bfn_out.flush()?;
+        bfdb_out.flush()?;
+        self.bfdata.flush()?;
+
+        Ok(self.bfdata)
+    }
+
+    // Where the next file's data starts
+    fn aligned_len(&self) -> u64 {
+        self.len.next_multiple_of(self.alignment)
+    }
+
+    // The path `archive_path` is stored under, e.g. `./textures/ui/logo.dds`
+    fn stored_path(&self, archive_path: &Path) -> Result<PathBuf> {
+        let mut path = PathBuf::from(ROOT);
+        for part in archive_path.to_string_lossy().split(['/', '\\']) {
+            if !part.is_empty() && part != "." {
+                path.push(part);
+            }
+        }
+
+        if path == Path::new(ROOT) {
+            return Err(BigFileError::EmptyPath(archive_path.to_path_buf()));
+        }
+
+        match self.indices.get(&normalize_path(&path)) {
+            Some(&i) if self.files[i].0 != path => Err(BigFileError::ConflictingPath {
+                path,
+                existing: self.files[i].0.clone(),
+            }),
+            _ => Ok(path),
+        }
+    }
+
+    // Takes a path from `stored_path`
+    fn insert(&mut self, path: PathBuf, offset: u64, size: u64) {
+        let entry = Entry { offset, size };
+        match self.indices.get(&normalize_path(&path)) {
+            Some(&i) => self.files[i].1 = entry,
+            None => {
+                self.indices.insert(normalize_path(&path), self.files.len());
+                self.files.push((path, entry));
+            }
+        }
+    }
+}
+
+fn insert_path(root: &mut BfnDir, path: &Path) {
+    let parts: Vec<String> = path
+        .iter()
+        .skip(1)
+        .map(|p| p.to_string_lossy().to_string())
+        .collect();
+
+    if let Some((file, dirs)) = parts.split_last() {
+        let mut dir = root;
+        for name in dirs {
+            dir = dir.dir_mut(name);
+        }
+        dir.files.push(file.clone());
+    }
+}
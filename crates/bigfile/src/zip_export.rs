@@ -0,0 +1,52 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter},
+    path::PathBuf,
+};
+
+use zip::{ZipWriter, write::SimpleFileOptions};
+
+use crate::{BigFile, error::IoResultExt, sanitize_path};
+
+// Entries from this size on need zip64 headers
+const ZIP64_THRESHOLD: u64 = u32::MAX as u64;
+
+impl BigFile {
+    /// Writes every entry into a single deflated zip instead of a directory, keeping the
+    /// directory structure. Entries are streamed in, so they're never all in memory.
+    pub fn extract_to_zip(&self, zip_path: PathBuf) -> crate::Result<()> {
+        self.extract_to_zip_with_level(zip_path, None)
+    }
+
+    /// Like `extract_to_zip`, with a deflate level from 0 (stored) to 9 (smallest).
+    /// `None` uses the default level.
+    pub fn extract_to_zip_with_level(
+        &self,
+        zip_path: PathBuf,
+        level: Option<i64>,
+    ) -> crate::Result<()> {
+        let file = File::create(&zip_path).with_file(zip_path.clone())?;
+        let mut zip = ZipWriter::new(BufWriter::new(file));
+        let options = SimpleFileOptions::default().compression_level(level);
+
+        // Sorted, so the same bigfile always gives the same zip
+        let mut entries: Vec<_> = self.entries.iter().collect();
+        entries.sort_by_key(|(path, _)| *path);
+
+        for (path, entry) in entries {
+            // Zips always use forward slashes
+            let name = sanitize_path(path)?.to_string_lossy().replace('\\', "/");
+            let options = options.large_file(entry.size >= ZIP64_THRESHOLD);
+
+            zip.start_file(name, options)
+                .map_err(io::Error::from)
+                .with_file(zip_path.clone())?;
+            self.copy_entry(path, entry, &mut zip)?;
+            log::trace!("zipped {}", path.display());
+        }
+
+        zip.finish().map_err(io::Error::from).with_file(zip_path)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,58 @@
+// Each test binary only uses some of these
+#![allow(dead_code)]
+
+use std::{
+    fs::File,
+    io::BufWriter,
+    path::{Path, PathBuf},
+};
+
+use bigfile::{BigFile, BigFileWriter};
+use tempfile::TempDir;
+
+/// Writes a bigfile with `files` to a temporary directory and opens it.
+/// The directory is removed when the returned `TempDir` is dropped.
+pub fn write_bigfile(files: &[(&str, &[u8])]) -> (TempDir, BigFile) {
+    let dir = TempDir::new().unwrap();
+    let bfn = write_files(dir.path(), files);
+
+    let bigfile = BigFile::open(bfn).unwrap();
+    (dir, bigfile)
+}
+
+/// Writes `bigfile.bfn`, `.bfdb` and `.bfdata` with `files` to `dir`, replacing any
+/// that are already there. Returns the path of the bfn.
+pub fn write_files(dir: &Path, files: &[(&str, &[u8])]) -> PathBuf {
+    let mut writer = BigFileWriter::new();
+    for (path, data) in files {
+        writer.add_file(PathBuf::from(path), data).unwrap();
+    }
+
+    let bfn = dir.join("bigfile.bfn");
+    let create = |path: &Path| BufWriter::new(File::create(path).unwrap());
+    writer
+        .write(
+            &mut create(&bfn),
+            &mut create(&bfn.with_extension("bfdb")),
+            &mut create(&bfn.with_extension("bfdata")),
+        )
+        .unwrap();
+
+    bfn
+}
+
+/// Checks that the bigfile holds exactly `files`, and that each of them reads back unchanged
+pub fn assert_contents(bigfile: &BigFile, files: &[(&str, &[u8])]) {
+    let mut expected: Vec<_> = files
+        .iter()
+        .map(|(path, _)| Path::new(".").join(path))
+        .collect();
+    expected.sort();
+    let mut paths: Vec<_> = bigfile.paths().cloned().collect();
+    paths.sort();
+    assert_eq!(paths, expected);
+
+    for (path, data) in files {
+        assert_eq!(bigfile.get(Path::new(path)).unwrap(), *data, "{path}");
+    }
+}
@@ -0,0 +1,50 @@
+mod common;
+
+use std::{fs, io::Cursor, path::Path};
+
+use bigfile::{BigFileOptions, Endianness, bfdb::Bfdb, bfn::Bfn};
+use common::write_bigfile;
+
+#[test]
+fn big_endian() {
+    let (dir, _) = write_bigfile(&[("a.txt", b"hello"), ("dir/b.txt", b"world")]);
+    let bfn_path = dir.path().join("bigfile.bfn");
+    let bfdb_path = dir.path().join("bigfile.bfdb");
+
+    let bfn = Bfn::read(&mut fs::File::open(&bfn_path).unwrap()).unwrap();
+    let mut out = Vec::new();
+    bfn.write_with_endianness(&mut out, Endianness::Big)
+        .unwrap();
+    fs::write(&bfn_path, &out).unwrap();
+
+    // Reading it back the same way gives the same bytes
+    let reread = Bfn::read_with_endianness(&mut Cursor::new(&out), Endianness::Big).unwrap();
+    assert_eq!(reread.files, bfn.files);
+    let mut again = Vec::new();
+    reread
+        .write_with_endianness(&mut again, Endianness::Big)
+        .unwrap();
+    assert_eq!(again, out);
+
+    let bfdb = Bfdb::read(&mut fs::File::open(&bfdb_path).unwrap()).unwrap();
+    let mut out = Vec::new();
+    bfdb.write_with_endianness(&mut out, Endianness::Big)
+        .unwrap();
+    fs::write(&bfdb_path, &out).unwrap();
+
+    let reread = Bfdb::read_with_endianness(&mut Cursor::new(&out), Endianness::Big).unwrap();
+    assert_eq!(reread.records, bfdb.records);
+
+    let bfdata_path = dir.path().join("bigfile.bfdata");
+    let options = BigFileOptions::new();
+    assert!(
+        options
+            .open(bfn_path.clone(), bfdb_path.clone(), bfdata_path.clone())
+            .is_err()
+    );
+
+    let options = options.endianness(Endianness::Big);
+    let bigfile = options.open(bfn_path, bfdb_path, bfdata_path).unwrap();
+    assert_eq!(bigfile.get(Path::new("a.txt")).unwrap(), b"hello");
+    assert_eq!(bigfile.get(Path::new("dir/b.txt")).unwrap(), b"world");
+}
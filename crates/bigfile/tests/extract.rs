@@ -0,0 +1,432 @@
+mod common;
+
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::{Duration, SystemTime},
+};
+
+use bigfile::{
+    BigFile, BigFileOptions, DEFAULT_ROOT_STRIP, ExtractOptions, Overwrite, bfdb::Bfdb, bfn::Bfn,
+    common_prefix, error::BigFileError,
+};
+use common::{write_bigfile, write_files};
+use tempfile::TempDir;
+
+#[test]
+fn cancel_stops_before_the_next_entry() {
+    let (_dir, bigfile) = write_bigfile(&[("a.txt", b"a"), ("b.txt", b"b"), ("c.txt", b"c")]);
+    let out = TempDir::new().unwrap();
+
+    let cancel = Arc::new(AtomicBool::new(false));
+    let flag = Arc::clone(&cancel);
+    let options = ExtractOptions::new()
+        .output(out.path())
+        .progress(move |done, _| flag.store(done == 1, Ordering::Relaxed))
+        .cancel(cancel);
+
+    let report = bigfile.extract_with(&options).unwrap();
+    assert!(report.cancelled);
+    assert_eq!(report.written.len(), 1);
+}
+
+#[test]
+fn progress_counts_every_entry_across_threads() {
+    let files: Vec<_> = (0..32).map(|i| format!("dir{}/file{i}", i % 4)).collect();
+    let files: Vec<_> = files.iter().map(|p| (p.as_str(), &b"data"[..])).collect();
+    let (_dir, bigfile) = write_bigfile(&files);
+    let out = TempDir::new().unwrap();
+
+    let finished = Arc::new(AtomicBool::new(false));
+    let flag = Arc::clone(&finished);
+    let options = ExtractOptions::new()
+        .output(out.path())
+        .threads(4)
+        .progress(move |done, total| {
+            if done == total {
+                flag.store(true, Ordering::Relaxed);
+            }
+        });
+
+    let report = bigfile.extract_with(&options).unwrap();
+    assert_eq!(report.written.len(), 32);
+    assert!(!report.cancelled);
+    assert!(finished.load(Ordering::Relaxed));
+}
+
+#[test]
+fn flatten_numbers_files_with_the_same_name() {
+    let (_dir, bigfile) = write_bigfile(&[
+        ("a/logo.dds", b"a"),
+        ("b/LOGO.dds", b"b"),
+        ("c/logo.dds", b"c"),
+        ("c/readme", b"readme"),
+    ]);
+    let out = TempDir::new().unwrap();
+
+    let options = ExtractOptions::new()
+        .output(out.path())
+        .flatten(true)
+        .threads(4);
+    let mut report = bigfile.extract_with(&options).unwrap();
+    report.written.sort();
+
+    let names = ["LOGO (2).dds", "logo (3).dds", "logo.dds", "readme"];
+    assert_eq!(report.written, names.map(|name| out.path().join(name)));
+    assert_eq!(fs::read(out.path().join("logo.dds")).unwrap(), b"a");
+    assert_eq!(fs::read(out.path().join("LOGO (2).dds")).unwrap(), b"b");
+    assert_eq!(fs::read(out.path().join("logo (3).dds")).unwrap(), b"c");
+}
+
+#[test]
+fn extract_paths_strips_the_common_prefix() {
+    let (_dir, bigfile) = write_bigfile(&[
+        ("textures/ui/a.dds", b"a"),
+        ("textures/ui/b/c.dds", b"c"),
+        ("textures/other.dds", b"other"),
+    ]);
+    let paths = [
+        PathBuf::from("Textures/UI/A.dds"),
+        PathBuf::from("textures\\ui\\b\\c.dds"),
+    ];
+
+    let out = TempDir::new().unwrap();
+    let report = bigfile
+        .extract_paths(&paths, out.path().to_path_buf(), true)
+        .unwrap();
+    assert_eq!(report.written.len(), 2);
+    assert_eq!(fs::read(out.path().join("a.dds")).unwrap(), b"a");
+    assert_eq!(fs::read(out.path().join("b/c.dds")).unwrap(), b"c");
+    assert!(!out.path().join("other.dds").exists());
+
+    let out = TempDir::new().unwrap();
+    bigfile
+        .extract_paths(&paths, out.path().to_path_buf(), false)
+        .unwrap();
+    assert!(out.path().join("textures/ui/b/c.dds").exists());
+}
+
+#[test]
+fn common_prefix_of_parents() {
+    let prefix = common_prefix([Path::new("a/b/c.txt"), Path::new("a/b/d/e.txt")]);
+    assert_eq!(prefix, Path::new("a/b"));
+    assert_eq!(
+        common_prefix([Path::new("a/b.txt"), Path::new("c/d.txt")]),
+        Path::new("")
+    );
+    assert_eq!(common_prefix([]), Path::new(""));
+}
+
+#[test]
+fn resume_skips_files_that_are_already_complete() {
+    let (_dir, bigfile) = write_bigfile(&[
+        ("done.txt", b"done"),
+        ("partial.txt", b"partial"),
+        ("missing.txt", b"missing"),
+    ]);
+    let out = TempDir::new().unwrap();
+    // Same size as the entry, so it's taken as complete even though the data differs
+    fs::write(out.path().join("done.txt"), b"DONE").unwrap();
+    fs::write(out.path().join("partial.txt"), b"part").unwrap();
+
+    let options = ExtractOptions::new().output(out.path()).resume(true);
+    let report = bigfile.extract_with(&options).unwrap();
+    assert_eq!(report.complete, 1);
+    assert_eq!(report.written.len(), 2);
+    assert_eq!(fs::read(out.path().join("done.txt")).unwrap(), b"DONE");
+    assert_eq!(
+        fs::read(out.path().join("partial.txt")).unwrap(),
+        b"partial"
+    );
+    assert_eq!(
+        fs::read(out.path().join("missing.txt")).unwrap(),
+        b"missing"
+    );
+}
+
+#[test]
+fn backslashes_in_names_become_directories() {
+    let (dir, _) = write_bigfile(&[("textures/ui/logo.dds", b"logo")]);
+    let bfn_path = dir.path().join("bigfile.bfn");
+
+    // The whole path as a single name in the root, the way some bfns store it.
+    // The hash stays the same, as backslashes are hashed as forward slashes.
+    let mut bfn = Bfn::read(&mut File::open(&bfn_path).unwrap()).unwrap();
+    bfn.root.dirs.clear();
+    bfn.root.files = vec!["textures\\ui\\logo.dds".to_string()];
+    bfn.write(&mut File::create(&bfn_path).unwrap()).unwrap();
+
+    let bigfile = BigFile::open(bfn_path).unwrap();
+    let out = TempDir::new().unwrap();
+    bigfile.extract(out.path().to_path_buf()).unwrap();
+
+    assert_eq!(
+        fs::read(out.path().join("textures/ui/logo.dds")).unwrap(),
+        b"logo"
+    );
+    assert!(out.path().join("textures/ui").is_dir());
+    assert_eq!(fs::read_dir(out.path()).unwrap().count(), 1);
+}
+
+#[test]
+fn extract_subtree_matches_any_casing() {
+    let (_dir, bigfile) = write_bigfile(&[
+        ("textures/ui/a.dds", b"a"),
+        ("textures/ui/icons/b.dds", b"b"),
+        ("textures/uiextra/c.dds", b"c"),
+        ("sounds/d.ogg", b"d"),
+    ]);
+
+    let out = TempDir::new().unwrap();
+    let extracted = bigfile
+        .extract_subtree(Path::new("Textures\\UI"), out.path().to_path_buf(), true)
+        .unwrap();
+    assert_eq!(extracted, 2);
+    assert_eq!(fs::read(out.path().join("a.dds")).unwrap(), b"a");
+    assert_eq!(fs::read(out.path().join("icons/b.dds")).unwrap(), b"b");
+    assert!(!out.path().join("textures").exists());
+
+    let out = TempDir::new().unwrap();
+    let extracted = bigfile
+        .extract_subtree(Path::new("textures/"), out.path().to_path_buf(), false)
+        .unwrap();
+    assert_eq!(extracted, 3);
+    assert!(out.path().join("textures/uiextra/c.dds").exists());
+    assert!(!out.path().join("sounds").exists());
+}
+
+#[test]
+fn extract_subtree_with_or_without_the_root() {
+    let dir = TempDir::new().unwrap();
+    let bfn_path = write_files(
+        dir.path(),
+        &[("textures/ui/a.dds", b"a"), ("sounds/b.ogg", b"b")],
+    );
+
+    // The bfdb's hashes stay the same, as they don't include the root
+    let mut bfn = Bfn::read(&mut File::open(&bfn_path).unwrap()).unwrap();
+    bfn.root.name = "data".to_string();
+    bfn.write(&mut File::create(&bfn_path).unwrap()).unwrap();
+    let bigfile = BigFileOptions::new()
+        .root_strip(5)
+        .open(
+            bfn_path.clone(),
+            bfn_path.with_extension("bfdb"),
+            bfn_path.with_extension("bfdata"),
+        )
+        .unwrap();
+
+    for prefix in ["textures/ui", "Data/Textures/UI"] {
+        let out = TempDir::new().unwrap();
+        let extracted = bigfile
+            .extract_subtree(Path::new(prefix), out.path().to_path_buf(), true)
+            .unwrap();
+        assert_eq!(extracted, 1, "{prefix}");
+        assert_eq!(fs::read(out.path().join("a.dds")).unwrap(), b"a");
+    }
+
+    let out = TempDir::new().unwrap();
+    let extracted = bigfile
+        .extract_subtree(Path::new("textures"), out.path().to_path_buf(), false)
+        .unwrap();
+    assert_eq!(extracted, 1);
+    assert!(out.path().join("data/textures/ui/a.dds").exists());
+}
+
+#[test]
+fn extract_plan_matches_what_is_written() {
+    let (_dir, bigfile) = write_bigfile(&[
+        ("a.txt", b"a"),
+        ("dir/b.txt", b"bb"),
+        ("dir/sub/c.bin", b"ccc"),
+        ("empty", b""),
+    ]);
+    let out = TempDir::new().unwrap();
+
+    let plan = bigfile.extract_plan(out.path().to_path_buf()).unwrap();
+    assert_eq!(fs::read_dir(out.path()).unwrap().count(), 0);
+
+    let mut report = bigfile
+        .extract_with(&ExtractOptions::new().output(out.path()))
+        .unwrap();
+    report.written.sort();
+
+    let planned: Vec<_> = plan.iter().map(|(path, _)| path.clone()).collect();
+    assert_eq!(planned, report.written);
+    for (path, size) in &plan {
+        assert_eq!(
+            fs::metadata(path).unwrap().len(),
+            *size,
+            "{}",
+            path.display()
+        );
+    }
+}
+
+#[test]
+fn overwrite_policies() {
+    let files: [(&str, &[u8]); 3] = [("old.txt", b"a"), ("new.txt", b"b"), ("missing.txt", b"c")];
+    let (dir, bigfile) = write_bigfile(&files);
+    let modified = fs::metadata(dir.path().join("bigfile.bfdata"))
+        .unwrap()
+        .modified()
+        .unwrap();
+
+    // Policy, and whether `old.txt` and `new.txt` are written over
+    let cases = [
+        (Overwrite::Always, true, true),
+        (Overwrite::Never, false, false),
+        (Overwrite::IfNewer, true, false),
+    ];
+    for (overwrite, old_written, new_written) in cases {
+        let out = TempDir::new().unwrap();
+        let existing = |name: &str, mtime: SystemTime| {
+            let path = out.path().join(name);
+            fs::write(&path, "existing").unwrap();
+            File::options()
+                .write(true)
+                .open(&path)
+                .unwrap()
+                .set_modified(mtime)
+                .unwrap();
+            path
+        };
+        let old = existing("old.txt", SystemTime::UNIX_EPOCH);
+        let new = existing("new.txt", modified + Duration::from_secs(3600));
+        let missing = out.path().join("missing.txt");
+
+        let mut report = bigfile
+            .extract_with(
+                &ExtractOptions::new()
+                    .output(out.path())
+                    .overwrite(overwrite),
+            )
+            .unwrap();
+        report.written.sort();
+        report.skipped.sort();
+
+        let mut written = vec![missing.clone()];
+        let mut skipped = Vec::new();
+        for (path, expected) in [(&old, old_written), (&new, new_written)] {
+            if expected {
+                written.push(path.clone());
+            } else {
+                skipped.push(path.clone());
+            }
+        }
+        written.sort();
+        skipped.sort();
+
+        assert_eq!(report.written, written, "{overwrite:?}");
+        assert_eq!(report.skipped, skipped, "{overwrite:?}");
+        assert_eq!(fs::read(&missing).unwrap(), b"c", "{overwrite:?}");
+        let contents = |written: bool, data: &[u8]| {
+            if written {
+                data.to_vec()
+            } else {
+                b"existing".to_vec()
+            }
+        };
+        assert_eq!(
+            fs::read(&old).unwrap(),
+            contents(old_written, b"a"),
+            "{overwrite:?}"
+        );
+        assert_eq!(
+            fs::read(&new).unwrap(),
+            contents(new_written, b"b"),
+            "{overwrite:?}"
+        );
+    }
+}
+
+#[test]
+fn extracted_files_get_the_given_mtimes() {
+    let (_dir, bigfile) = write_bigfile(&[("a.txt", b"a"), ("dir/b.txt", b"b")]);
+    let mtime = |secs| SystemTime::UNIX_EPOCH + Duration::from_secs(secs);
+    let modified = |path: PathBuf| fs::metadata(path).unwrap().modified().unwrap();
+
+    // From the map, in another casing and with backslashes, and the default for the rest
+    let out = TempDir::new().unwrap();
+    let options = ExtractOptions::new()
+        .output(out.path())
+        .mtimes(HashMap::from([(
+            PathBuf::from(".\\DIR\\B.txt"),
+            mtime(1_000_000),
+        )]))
+        .default_mtime(mtime(2_000_000));
+    bigfile.extract_with(&options).unwrap();
+    assert_eq!(modified(out.path().join("dir/b.txt")), mtime(1_000_000));
+    assert_eq!(modified(out.path().join("a.txt")), mtime(2_000_000));
+
+    // Only the default
+    let out = TempDir::new().unwrap();
+    let options = ExtractOptions::new()
+        .output(out.path())
+        .default_mtime(mtime(3_000_000));
+    bigfile.extract_with(&options).unwrap();
+    assert_eq!(modified(out.path().join("dir/b.txt")), mtime(3_000_000));
+    assert_eq!(modified(out.path().join("a.txt")), mtime(3_000_000));
+}
+
+#[test]
+fn extract_report_keeps_going_past_failures() {
+    let (dir, _) = write_bigfile(&[("a.txt", b"hello"), ("b.txt", b"world")]);
+    let bfdata_path = dir.path().join("bigfile.bfdata");
+    let bfdata = fs::read(&bfdata_path).unwrap();
+    fs::write(&bfdata_path, &bfdata[..7]).unwrap();
+    let bigfile = BigFile::open(dir.path().join("bigfile.bfn")).unwrap();
+
+    let out = TempDir::new().unwrap();
+    let report = bigfile.extract_report(out.path().to_path_buf()).unwrap();
+    assert_eq!(report.written, [out.path().join("a.txt")]);
+    assert_eq!(report.failed.len(), 1);
+    assert_eq!(report.failed[0].0, Path::new("./b.txt"));
+    assert!(matches!(
+        report.failed[0].1,
+        BigFileError::Truncated { available: 2, .. }
+    ));
+    assert!(!out.path().join("b.txt").exists());
+
+    assert_eq!(bigfile.extract_lossy(out.path().to_path_buf()), Ok(1));
+}
+
+#[test]
+fn unsafe_names_fail_to_extract() {
+    for name in ["../evil.txt", "..\\evil.txt", "/evil.txt", "C:\\evil.txt"] {
+        let dir = TempDir::new().unwrap();
+        let bfn_path = write_files(dir.path(), &[("placeholder.txt", b"evil")]);
+
+        // The name straight in the root, with the placeholder's bfdb entry moved over to it
+        let mut bfn = Bfn::read(&mut File::open(&bfn_path).unwrap()).unwrap();
+        bfn.root.files = vec![name.to_string()];
+        bfn.write(&mut File::create(&bfn_path).unwrap()).unwrap();
+        let path = Bfn::read(&mut File::open(&bfn_path).unwrap())
+            .unwrap()
+            .files
+            .remove(0);
+
+        let bfdb_path = bfn_path.with_extension("bfdb");
+        let mut bfdb = Bfdb::read(&mut File::open(&bfdb_path).unwrap()).unwrap();
+        bfdb.records[0].0 = bigfile_core::entry_hash(path.to_str().unwrap(), DEFAULT_ROOT_STRIP);
+        bfdb.write(&mut File::create(&bfdb_path).unwrap()).unwrap();
+
+        let bigfile = BigFile::open(bfn_path).unwrap();
+        let out = dir.path().join("out");
+        fs::create_dir(&out).unwrap();
+        assert_eq!(
+            bigfile.extract(out.clone()),
+            Err(BigFileError::UnsafePath(path)),
+            "{name}"
+        );
+
+        // Nothing in the output directory, and nothing next to it but the bigfile
+        assert_eq!(fs::read_dir(&out).unwrap().count(), 0, "{name}");
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 4, "{name}");
+    }
+}
@@ -0,0 +1,1121 @@
+mod common;
+
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{self, BufReader, Cursor, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+};
+
+use bigfile::{
+    BigFile, BigFileOptions, BigFileWriter, Change, DataSource, Gap, Part, Stats, bfdb::Bfdb,
+    bfn::Bfn, error::BigFileError,
+};
+use common::{assert_contents, write_bigfile, write_files};
+use tempfile::TempDir;
+
+fn roundtrip(files: &[(&str, &[u8])]) {
+    let (_dir, bigfile) = write_bigfile(files);
+    assert_contents(&bigfile, files);
+}
+
+#[test]
+fn empty() {
+    let (_dir, bigfile) = write_bigfile(&[]);
+    assert!(bigfile.is_empty());
+}
+
+#[test]
+fn file_at_root() {
+    roundtrip(&[("readme.txt", b"hello")]);
+}
+
+#[test]
+fn nested_directories() {
+    roundtrip(&[
+        ("textures/ui/logo.dds", b"logo"),
+        ("textures/ui/icons/close.dds", b"close"),
+        ("textures/world.dds", b"world"),
+        ("sounds/jump.wav", b"jump"),
+        ("top.txt", b"top"),
+    ]);
+}
+
+#[test]
+fn zero_byte_entry() {
+    roundtrip(&[
+        ("empty.bin", b""),
+        ("dir/also_empty.bin", b""),
+        ("full.bin", b"data"),
+    ]);
+}
+
+#[test]
+fn zero_byte_entry_extracts_to_empty_file() {
+    let (_dir, bigfile) = write_bigfile(&[("dir/empty.bin", b""), ("full.bin", b"data")]);
+    let out = TempDir::new().unwrap();
+
+    bigfile.extract(out.path().to_path_buf()).unwrap();
+
+    assert_eq!(fs::read(out.path().join("dir/empty.bin")).unwrap(), b"");
+    assert_eq!(fs::read(out.path().join("full.bin")).unwrap(), b"data");
+}
+
+#[test]
+fn zero_byte_entry_past_the_end() {
+    let (dir, _) = write_bigfile(&[("full.bin", b"data"), ("empty.bin", b"")]);
+    let bfdb_path = dir.path().join("bigfile.bfdb");
+
+    let mut bfdb = Bfdb::read(&mut File::open(&bfdb_path).unwrap()).unwrap();
+    for (_, entry) in &mut bfdb.records {
+        if entry.size == 0 {
+            entry.offset = 1000;
+        }
+    }
+    bfdb.write(&mut File::create(&bfdb_path).unwrap()).unwrap();
+
+    let bigfile = BigFile::open(dir.path().join("bigfile.bfn")).unwrap();
+    assert_eq!(bigfile.get(Path::new("empty.bin")).unwrap(), b"");
+
+    let bfdata = fs::read(dir.path().join("bigfile.bfdata")).unwrap();
+    let bigfile = BigFile::from_paths(
+        dir.path().join("bigfile.bfn"),
+        bfdb_path,
+        DataSource::Buffer(bfdata.into()),
+    )
+    .unwrap();
+    assert_eq!(bigfile.get(Path::new("empty.bin")).unwrap(), b"");
+}
+
+#[test]
+fn empty_directory() {
+    let files: &[(&str, &[u8])] = &[("dir/file.txt", b"file")];
+    let (dir, _) = write_bigfile(files);
+    let bfn_path = dir.path().join("bigfile.bfn");
+
+    let mut bfn = Bfn::read(&mut File::open(&bfn_path).unwrap()).unwrap();
+    bfn.root.dir_mut("empty").dir_mut("nested");
+    bfn.root.dir_mut("dir").dir_mut("empty");
+    bfn.write(&mut File::create(&bfn_path).unwrap()).unwrap();
+
+    let bigfile = BigFile::open(bfn_path).unwrap();
+    assert_contents(&bigfile, files);
+
+    let out = TempDir::new().unwrap();
+    bigfile.extract(out.path().to_path_buf()).unwrap();
+    assert_eq!(fs::read(out.path().join("dir/file.txt")).unwrap(), b"file");
+}
+
+#[test]
+fn lookups_ignore_case_and_slashes() {
+    let (_dir, bigfile) = write_bigfile(&[("Textures/UI/Logo.dds", b"logo")]);
+
+    assert_eq!(
+        bigfile.get(Path::new("textures\\ui\\logo.DDS")).unwrap(),
+        b"logo"
+    );
+    assert_eq!(
+        bigfile.get(Path::new("missing.dds")),
+        Err(BigFileError::EntryNotFound("missing.dds".into()))
+    );
+}
+
+#[test]
+fn verify_all_finds_truncated_entries() {
+    let (dir, bigfile) = write_bigfile(&[("a.txt", b"hello"), ("b.txt", b"world")]);
+    assert_eq!(bigfile.verify_all().unwrap().verified, 2);
+
+    let bfdata_path = dir.path().join("bigfile.bfdata");
+    let bfdata = fs::read(&bfdata_path).unwrap();
+    fs::write(&bfdata_path, &bfdata[..7]).unwrap();
+
+    let bigfile = BigFile::open(dir.path().join("bigfile.bfn")).unwrap();
+    let report = bigfile.verify_all().unwrap();
+    assert_eq!(report.verified, 1);
+    assert_eq!(
+        report.failed,
+        [(
+            Path::new("./b.txt").to_path_buf(),
+            BigFileError::Truncated {
+                path: "./b.txt".into(),
+                offset: 5,
+                wanted: 5,
+                available: 2,
+            }
+        )]
+    );
+}
+
+#[test]
+fn longer_root_directory() {
+    let (dir, _) = write_bigfile(&[("textures/logo.dds", b"logo")]);
+    let bfn_path = dir.path().join("bigfile.bfn");
+    let bfdb_path = dir.path().join("bigfile.bfdb");
+    let bfdata_path = dir.path().join("bigfile.bfdata");
+
+    // The bfdb's hashes stay the same, as they don't include the root
+    let mut bfn = Bfn::read(&mut File::open(&bfn_path).unwrap()).unwrap();
+    bfn.root.name = "data".to_string();
+    bfn.write(&mut File::create(&bfn_path).unwrap()).unwrap();
+
+    assert!(matches!(
+        BigFile::from_paths(
+            bfn_path.clone(),
+            bfdb_path.clone(),
+            DataSource::File(bfdata_path.clone())
+        ),
+        Err(BigFileError::HashEntryNotFound(_))
+    ));
+
+    let bfdata = DataSource::File(bfdata_path);
+    let bigfile = BigFile::from_paths_with_root_strip(bfn_path, bfdb_path, bfdata, 5).unwrap();
+    assert_eq!(
+        bigfile.get(Path::new("data/textures/logo.dds")).unwrap(),
+        b"logo"
+    );
+
+    // Other spellings are looked up by hash, with or without the root
+    assert_eq!(bigfile.get("Data/Textures/Logo.dds").unwrap(), b"logo");
+    assert_eq!(bigfile.get("textures\\LOGO.dds").unwrap(), b"logo");
+    assert_eq!(
+        bigfile.hash_of("DATA/textures/logo.dds"),
+        bigfile.hash_of("textures/logo.dds")
+    );
+}
+
+#[test]
+fn lookups_without_root_strip() {
+    let (dir, _) = write_bigfile(&[("textures/logo.dds", b"logo")]);
+    let bfn_path = dir.path().join("bigfile.bfn");
+    let bfdb_path = dir.path().join("bigfile.bfdb");
+
+    // Hashed with the root and all
+    let hash = bigfile_core::entry_hash("./textures/logo.dds", 0);
+    let mut bfdb = Bfdb::read(&mut File::open(&bfdb_path).unwrap()).unwrap();
+    bfdb.records[0].0 = hash;
+    bfdb.write(&mut File::create(&bfdb_path).unwrap()).unwrap();
+
+    let bfdata_path = dir.path().join("bigfile.bfdata");
+    let bigfile = BigFileOptions::new()
+        .root_strip(0)
+        .open(bfn_path, bfdb_path, bfdata_path)
+        .unwrap();
+
+    assert_eq!(bigfile.hash_of("Textures\\Logo.DDS"), hash);
+    assert_eq!(
+        bigfile.find("Textures\\Logo.DDS"),
+        Some(&PathBuf::from("./textures/logo.dds"))
+    );
+    assert_eq!(bigfile.get("textures/logo.dds").unwrap(), b"logo");
+    assert_eq!(
+        bigfile.get_normalized("./TEXTURES/logo.dds").unwrap(),
+        b"logo"
+    );
+
+    let candidates = ["logo.dds", "Textures/Logo.dds"].map(String::from);
+    assert_eq!(
+        bigfile.resolve_hashes(&[hash], candidates.into_iter()),
+        [(hash, "Textures/Logo.dds".to_string())].into()
+    );
+}
+
+#[test]
+fn options_open_in_memory_with_lossy_strings() {
+    let (dir, _) = write_bigfile(&[("name.txt", b"data")]);
+    let bfn_path = dir.path().join("bigfile.bfn");
+    let bfdb_path = dir.path().join("bigfile.bfdb");
+    let bfdata_path = dir.path().join("bigfile.bfdata");
+
+    // The bfdb's hash is of the original name, so this only breaks the name itself
+    let mut bfn = fs::read(&bfn_path).unwrap();
+    let at = bfn.windows(8).position(|w| w == b"name.txt").unwrap();
+    bfn[at] = 0xFF;
+    fs::write(&bfn_path, bfn).unwrap();
+
+    let options = BigFileOptions::new().in_memory(true);
+    assert!(
+        options
+            .open(bfn_path.clone(), bfdb_path.clone(), bfdata_path.clone())
+            .is_err()
+    );
+
+    let options = options.lossy_strings(true).collect_missing(true);
+    match options.open(bfn_path, bfdb_path, bfdata_path) {
+        Err(BigFileError::MissingEntries { missing, total }) => {
+            assert_eq!(missing, [Path::new("./\u{FFFD}ame.txt")]);
+            assert_eq!(total, 1);
+        }
+        _ => panic!("expected the renamed entry to be missing"),
+    }
+}
+
+#[test]
+fn display_summary() {
+    let big = vec![0; 3 * 1024];
+    let (_dir, bigfile) =
+        write_bigfile(&[("a.txt", b"hello"), ("b/big.bin", &big), ("c.txt", b"")]);
+
+    assert_eq!(bigfile.to_string(), "3 entries, 3.0 KB");
+    assert_eq!(
+        format!("{bigfile:#}"),
+        "3 entries, 3.0 KB\n3.0 KB\t./b/big.bin\n5 B\t./a.txt\n0 B\t./c.txt"
+    );
+}
+
+#[test]
+fn compact_removes_gaps() {
+    let files: &[(&str, &[u8])] = &[("a.txt", b"aaaa"), ("b.txt", b"bbbb"), ("c.txt", b"cc")];
+    let (dir, _) = write_bigfile(files);
+    let bfn_path = dir.path().join("bigfile.bfn");
+    let bfdb_path = dir.path().join("bigfile.bfdb");
+    let bfdata_path = dir.path().join("bigfile.bfdata");
+
+    let mut bigfile = BigFile::open(bfn_path.clone()).unwrap();
+    assert_eq!(bigfile.layout_report().unwrap(), []);
+
+    // Smaller data is padded in place, leaving two unused bytes after `b.txt`
+    bigfile.replace_in_place(Path::new("b.txt"), b"bb").unwrap();
+    let mut bfdata = Vec::new();
+    let mut bfdb = Vec::new();
+    bigfile
+        .patch(HashMap::new(), &mut bfdata, &mut bfdb)
+        .unwrap();
+    fs::write(&bfdb_path, bfdb).unwrap();
+
+    let bigfile = BigFile::open(bfn_path.clone()).unwrap();
+    assert_eq!(bigfile.layout_report().unwrap(), [Gap { start: 6, end: 8 }]);
+
+    let mut bfdata = Vec::new();
+    let mut bfdb = Vec::new();
+    bigfile.compact(&mut bfdata, &mut bfdb).unwrap();
+    fs::write(&bfdata_path, bfdata).unwrap();
+    fs::write(&bfdb_path, bfdb).unwrap();
+
+    let bigfile = BigFile::open(bfn_path).unwrap();
+    assert_eq!(bigfile.layout_report().unwrap(), []);
+    assert_contents(
+        &bigfile,
+        &[("a.txt", b"aaaa"), ("b.txt", b"bb"), ("c.txt", b"cc")],
+    );
+}
+
+#[test]
+fn compact_keeps_orphans() {
+    let dir = TempDir::new().unwrap();
+    let bfn_path = write_files(dir.path(), &[("a.txt", b"aaaa")]);
+    let bfdb_path = bfn_path.with_extension("bfdb");
+    let bfdata_path = bfn_path.with_extension("bfdata");
+
+    // Two unused bytes, then data that only an orphan record points at
+    let mut bfdata = fs::read(&bfdata_path).unwrap();
+    bfdata.extend(b"--orph");
+    fs::write(&bfdata_path, bfdata).unwrap();
+    let mut bfdb = Bfdb::read(&mut File::open(&bfdb_path).unwrap()).unwrap();
+    let orphan = bigfile::bfdb::Entry { offset: 6, size: 4 };
+    bfdb.records.push((0x1234, orphan));
+    bfdb.write(&mut File::create(&bfdb_path).unwrap()).unwrap();
+
+    let bigfile = BigFile::open(bfn_path.clone()).unwrap();
+    assert_eq!(bigfile.layout_report().unwrap(), [Gap { start: 4, end: 6 }]);
+
+    let mut bfdata = Vec::new();
+    let mut bfdb = Vec::new();
+    bigfile.compact(&mut bfdata, &mut bfdb).unwrap();
+
+    assert_eq!(bfdata, b"aaaaorph");
+    let records = Bfdb::read(&mut Cursor::new(&bfdb)).unwrap().records;
+    assert_eq!(records.len(), 2);
+    assert_eq!(
+        records[1],
+        (0x1234, bigfile::bfdb::Entry { offset: 4, size: 4 })
+    );
+}
+
+#[test]
+fn try_get_separates_missing_from_unreadable() {
+    let (dir, bigfile) = write_bigfile(&[("a.txt", b"hello"), ("b.txt", b"world")]);
+    assert!(bigfile.has(Path::new("A.TXT")));
+    assert!(!bigfile.has(Path::new("missing.txt")));
+    assert_eq!(
+        bigfile.try_get(Path::new("a.txt")).unwrap().unwrap(),
+        b"hello"
+    );
+    assert_eq!(bigfile.try_get(Path::new("missing.txt")), Ok(None));
+
+    let bfdata_path = dir.path().join("bigfile.bfdata");
+    fs::write(&bfdata_path, b"hello").unwrap();
+    let bigfile = BigFile::open(dir.path().join("bigfile.bfn")).unwrap();
+    assert!(matches!(
+        bigfile.try_get(Path::new("b.txt")),
+        Err(BigFileError::Truncated { .. })
+    ));
+}
+
+#[test]
+fn diff_finds_added_removed_and_changed_entries() {
+    let (_old_dir, old) = write_bigfile(&[
+        ("kept.txt", b"same"),
+        ("removed.txt", b"gone"),
+        ("resized.txt", b"short"),
+        ("modified.txt", b"abcd"),
+    ]);
+    let (_new_dir, new) = write_bigfile(&[
+        ("KEPT.txt", b"same"),
+        ("added.txt", b"new"),
+        ("resized.txt", b"longer"),
+        ("modified.txt", b"dcba"),
+    ]);
+
+    let diff = old.diff(&new);
+    let changes: Vec<_> = diff.iter().cloned().collect();
+    assert_eq!(
+        changes,
+        [
+            Change::Added(PathBuf::from("./added.txt")),
+            Change::Removed(PathBuf::from("./removed.txt")),
+            Change::Resized {
+                path: PathBuf::from("./resized.txt"),
+                old: 5,
+                new: 6,
+            },
+        ]
+    );
+    assert_eq!(
+        diff.to_string(),
+        "+ ./added.txt\n- ./removed.txt\n~ ./resized.txt (5 B -> 6 B)"
+    );
+
+    let diff = old.diff_contents(&new).unwrap();
+    assert_eq!(diff.len(), 4);
+    assert!(
+        diff.into_iter()
+            .any(|c| c == Change::Modified(PathBuf::from("./modified.txt")))
+    );
+}
+
+#[test]
+fn dynamic_data_source() {
+    let files: &[(&str, &[u8])] = &[("a.txt", b"hello"), ("dir/b.txt", b"world")];
+    let (dir, _) = write_bigfile(files);
+    let bfdata = File::open(dir.path().join("bigfile.bfdata")).unwrap();
+
+    let bigfile = BigFile::from_paths(
+        dir.path().join("bigfile.bfn"),
+        dir.path().join("bigfile.bfdb"),
+        DataSource::Dynamic(Box::new(BufReader::new(bfdata))),
+    )
+    .unwrap();
+    assert_contents(&bigfile, files);
+    assert_eq!(bigfile.verify_all().unwrap().verified, files.len());
+
+    let mut cursor = bigfile.entry_cursor(Path::new("dir/b.txt")).unwrap();
+    let mut data = String::new();
+    cursor.read_to_string(&mut data).unwrap();
+    assert_eq!(data, "world");
+}
+
+#[test]
+fn entry_cursors_share_the_bfdata_handle() {
+    let (_dir, bigfile) = write_bigfile(&[("a.txt", b"first entry"), ("b.txt", b"second entry")]);
+    let mut a = bigfile.entry_cursor(Path::new("a.txt")).unwrap();
+    let mut b = bigfile.entry_cursor(Path::new("b.txt")).unwrap();
+
+    let mut buf = [0; 6];
+    a.read_exact(&mut buf).unwrap();
+    assert_eq!(&buf, b"first ");
+    b.read_exact(&mut buf).unwrap();
+    assert_eq!(&buf, b"second");
+
+    let mut rest = String::new();
+    a.read_to_string(&mut rest).unwrap();
+    assert_eq!(rest, "entry");
+    b.seek(SeekFrom::End(-5)).unwrap();
+    rest.clear();
+    b.read_to_string(&mut rest).unwrap();
+    assert_eq!(rest, "entry");
+}
+
+#[test]
+fn lookups_accept_strings() {
+    let (_dir, bigfile) = write_bigfile(&[("textures/logo.dds", b"logo")]);
+    assert_eq!(bigfile.get("textures/logo.dds").unwrap(), b"logo");
+    assert_eq!(bigfile.get("Textures\\Logo.DDS").unwrap(), b"logo");
+    assert_eq!(bigfile.try_get(String::from("missing.dds")), Ok(None));
+    assert!(bigfile.has("./textures/logo.dds"));
+}
+
+#[test]
+fn reload_picks_up_changed_files() {
+    let (dir, mut bigfile) = write_bigfile(&[("a.txt", b"old"), ("b.txt", b"gone")]);
+    let bfn_path = dir.path().join("bigfile.bfn");
+    let mut in_memory = BigFileOptions::new()
+        .in_memory(true)
+        .open(
+            bfn_path.clone(),
+            bfn_path.with_extension("bfdb"),
+            bfn_path.with_extension("bfdata"),
+        )
+        .unwrap();
+
+    let files: &[(&str, &[u8])] = &[("a.txt", b"newer"), ("c.txt", b"added")];
+    write_files(dir.path(), files);
+
+    bigfile.reload().unwrap();
+    assert_contents(&bigfile, files);
+    in_memory.reload().unwrap();
+    assert_contents(&in_memory, files);
+
+    let bfdata = fs::read(bfn_path.with_extension("bfdata")).unwrap();
+    let mut from_memory = BigFile::new(
+        &mut Cursor::new(fs::read(&bfn_path).unwrap()),
+        &mut Cursor::new(fs::read(bfn_path.with_extension("bfdb")).unwrap()),
+        &mut Cursor::new(bfdata),
+    )
+    .unwrap();
+    assert_eq!(
+        from_memory.reload().unwrap_err().io_kind(),
+        Some(io::ErrorKind::Unsupported)
+    );
+}
+
+#[test]
+fn entry_range_points_into_the_bfdata() {
+    let (dir, bigfile) = write_bigfile(&[("a.txt", b"first"), ("b.txt", b"second")]);
+    let bfdata = fs::read(dir.path().join("bigfile.bfdata")).unwrap();
+
+    for (path, data) in [("a.txt", &b"first"[..]), ("b.txt", b"second")] {
+        let range = bigfile.entry_range(path).unwrap();
+        assert_eq!(&bfdata[range.start as usize..range.end as usize], data);
+    }
+
+    assert_eq!(
+        bigfile.entry_range("missing.txt"),
+        Err(BigFileError::EntryNotFound(PathBuf::from("missing.txt")))
+    );
+}
+
+#[test]
+fn huge_entry_size_is_truncated_not_allocated() {
+    let (dir, _) = write_bigfile(&[("a.txt", b"data")]);
+    let bfdb_path = dir.path().join("bigfile.bfdb");
+
+    let mut bfdb = Bfdb::read(&mut File::open(&bfdb_path).unwrap()).unwrap();
+    for (_, entry) in &mut bfdb.records {
+        entry.size = u64::MAX;
+    }
+    bfdb.write(&mut File::create(&bfdb_path).unwrap()).unwrap();
+
+    let bigfile = BigFile::open(dir.path().join("bigfile.bfn")).unwrap();
+    assert!(matches!(
+        bigfile.get("a.txt"),
+        Err(BigFileError::Truncated { available: 4, .. })
+    ));
+}
+
+#[test]
+fn stats_sizes_and_extensions() {
+    let (_dir, bigfile) = write_bigfile(&[
+        ("a.dds", &[0; 10]),
+        ("b.DDS", &[0; 30]),
+        ("c.txt", &[0; 20]),
+        ("d", &[0; 40]),
+    ]);
+
+    let stats = bigfile.stats();
+    assert_eq!(stats.entries, 4);
+    assert_eq!(stats.total_size, 100);
+    assert_eq!(stats.mean_size, 25.0);
+    assert_eq!(stats.median_size, 25);
+    assert_eq!(stats.largest, Some((PathBuf::from("./d"), 40)));
+    assert_eq!(
+        stats.by_extension,
+        [
+            ("".to_string(), 1),
+            ("dds".to_string(), 2),
+            ("txt".to_string(), 1)
+        ]
+        .into()
+    );
+    assert_eq!(
+        stats.extensions_by_count(),
+        [("dds", 2), ("", 1), ("txt", 1)]
+    );
+    assert_eq!(bigfile::extension(Path::new("./b.DDS")), "dds");
+    assert_eq!(bigfile::format_size(100), "100 B");
+    assert_eq!(bigfile::format_size(1536), "1.5 KB");
+
+    let (_dir, empty) = write_bigfile(&[]);
+    assert_eq!(empty.stats(), Stats::default());
+}
+
+#[test]
+fn orphan_hashes_are_bfdb_entries_without_a_path() {
+    let (dir, bigfile) = write_bigfile(&[("a.txt", b"data")]);
+    assert!(bigfile.orphan_hashes().is_empty());
+
+    let bfdb_path = dir.path().join("bigfile.bfdb");
+    let mut bfdb = Bfdb::read(&mut File::open(&bfdb_path).unwrap()).unwrap();
+    let entry = bfdb.records[0].1;
+    bfdb.records.push((0xDEADBEEF, entry));
+    bfdb.records.push((0x1234, entry));
+    bfdb.write(&mut File::create(&bfdb_path).unwrap()).unwrap();
+
+    let bigfile = BigFile::open(dir.path().join("bigfile.bfn")).unwrap();
+    assert_eq!(bigfile.orphan_hashes(), [0xDEADBEEF, 0x1234]);
+    assert_eq!(bigfile.get("a.txt").unwrap(), b"data");
+}
+
+#[test]
+fn resolve_hashes_from_candidates() {
+    let (_dir, bigfile) = write_bigfile(&[]);
+    let hashes = [
+        bigfile::hash_path(Path::new("textures/logo.dds")),
+        bigfile::hash_path(Path::new("sounds/jump.wav")),
+    ];
+    let candidates = ["nothing.txt", "./Textures\\Logo.DDS", "textures/logo.dds"];
+
+    let resolved = bigfile.resolve_hashes(&hashes, candidates.into_iter().map(String::from));
+    assert_eq!(
+        resolved,
+        [(hashes[0], "./Textures\\Logo.DDS".to_string())].into()
+    );
+}
+
+// Makes up its bytes as they're read, so the whole file is never in memory at once
+struct Pattern {
+    pos: u64,
+    len: u64,
+}
+
+impl Pattern {
+    fn byte(pos: u64) -> u8 {
+        (pos % 251) as u8
+    }
+}
+
+impl Read for Pattern {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = buf.len().min((self.len - self.pos) as usize);
+        for byte in &mut buf[..n] {
+            *byte = Pattern::byte(self.pos);
+            self.pos += 1;
+        }
+        Ok(n)
+    }
+}
+
+#[test]
+fn streaming_writer_copies_from_readers() {
+    let dir = TempDir::new().unwrap();
+    let bfn_path = dir.path().join("bigfile.bfn");
+    let len = 3 * 1024 * 1024 + 7;
+
+    let bfdata = File::create(bfn_path.with_extension("bfdata")).unwrap();
+    let mut writer = BigFileWriter::streaming(bfdata);
+    writer
+        .add_file_from_reader("small.txt".into(), &b"hello"[..], 5)
+        .unwrap();
+    writer
+        .add_file_from_reader("dir/big.bin".into(), Pattern { pos: 0, len }, len)
+        .unwrap();
+    writer
+        .finish(
+            &mut File::create(&bfn_path).unwrap(),
+            &mut File::create(bfn_path.with_extension("bfdb")).unwrap(),
+        )
+        .unwrap();
+
+    let bigfile = BigFile::open(bfn_path).unwrap();
+    assert_eq!(bigfile.get("small.txt").unwrap(), b"hello");
+
+    let mut cursor = bigfile.entry_cursor("dir/big.bin").unwrap();
+    let mut buf = vec![0; 64 * 1024];
+    let mut pos = 0;
+    loop {
+        let read = cursor.read(&mut buf).unwrap();
+        if read == 0 {
+            break;
+        }
+        for &byte in &buf[..read] {
+            assert_eq!(byte, Pattern::byte(pos), "at {pos}");
+            pos += 1;
+        }
+    }
+    assert_eq!(pos, len);
+}
+
+#[test]
+fn streaming_writer_rejects_short_readers() {
+    let mut writer = BigFileWriter::streaming(Vec::new());
+    let err = writer
+        .add_file_from_reader("a.txt".into(), &b"abc"[..], 10)
+        .unwrap_err();
+    assert_eq!(err.io_kind(), Some(io::ErrorKind::UnexpectedEof));
+}
+
+#[test]
+fn writer_rejects_empty_paths() {
+    let mut writer = BigFileWriter::new();
+    for path in ["", ".", "./", "/"] {
+        assert_eq!(
+            writer.add_file(path.into(), b"data"),
+            Err(BigFileError::EmptyPath(path.into()))
+        );
+        assert!(matches!(
+            writer.add_file_from_reader(path.into(), &b"data"[..], 4),
+            Err(BigFileError::EmptyPath(_))
+        ));
+    }
+
+    // Nothing was added, so there are no orphans either
+    let (mut bfn, mut bfdb, mut bfdata) = (Vec::new(), Vec::new(), Vec::new());
+    writer.write(&mut bfn, &mut bfdb, &mut bfdata).unwrap();
+    assert!(
+        Bfdb::read(&mut Cursor::new(bfdb))
+            .unwrap()
+            .records
+            .is_empty()
+    );
+}
+
+#[test]
+fn writer_rejects_paths_that_only_differ_in_casing() {
+    let mut writer = BigFileWriter::new();
+    writer.add_file("dir/A.txt".into(), b"first").unwrap();
+    assert_eq!(
+        writer.add_file("DIR\\a.txt".into(), b"second"),
+        Err(BigFileError::ConflictingPath {
+            path: "./DIR/a.txt".into(),
+            existing: "./dir/A.txt".into(),
+        })
+    );
+    // The same path again still replaces the data
+    writer.add_file("dir\\A.txt".into(), b"third").unwrap();
+
+    let dir = TempDir::new().unwrap();
+    let bfn_path = dir.path().join("bigfile.bfn");
+    writer
+        .write(
+            &mut File::create(&bfn_path).unwrap(),
+            &mut File::create(bfn_path.with_extension("bfdb")).unwrap(),
+            &mut File::create(bfn_path.with_extension("bfdata")).unwrap(),
+        )
+        .unwrap();
+
+    let bigfile = BigFile::open(bfn_path).unwrap();
+    assert_eq!(bigfile.len(), 1);
+    assert!(bigfile.orphan_hashes().is_empty());
+    assert_eq!(bigfile.get("dir/a.txt").unwrap(), b"third");
+}
+
+// The three parts written one after the other behind the header `from_combined` expects
+fn write_combined(path: &Path, files: &[(&str, &[u8])]) {
+    let mut writer = BigFileWriter::new();
+    for (file, data) in files {
+        writer.add_file(file.into(), data).unwrap();
+    }
+    let (mut bfn, mut bfdb, mut bfdata) = (Vec::new(), Vec::new(), Vec::new());
+    writer.write(&mut bfn, &mut bfdb, &mut bfdata).unwrap();
+
+    let bfn_start = 24;
+    let bfdb_start = bfn_start + bfn.len() as u64;
+    let bfdata_start = bfdb_start + bfdb.len() as u64;
+    let mut combined = Vec::new();
+    for start in [bfn_start, bfdb_start, bfdata_start] {
+        combined.extend(start.to_le_bytes());
+    }
+    combined.extend(bfn);
+    combined.extend(bfdb);
+    combined.extend(bfdata);
+    fs::write(path, combined).unwrap();
+}
+
+#[test]
+fn combined_file_roundtrip() {
+    let files: &[(&str, &[u8])] = &[("a.txt", b"hello"), ("dir/b.bin", &[7; 300])];
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("bigfile.big");
+    write_combined(&path, files);
+
+    let bigfile = BigFile::from_combined(path.clone()).unwrap();
+    assert_contents(&bigfile, files);
+
+    // Ranges are in the combined file, past the header, bfn and bfdb
+    let range = bigfile.entry_range("a.txt").unwrap();
+    let combined = fs::read(&path).unwrap();
+    assert_eq!(
+        &combined[range.start as usize..range.end as usize],
+        b"hello"
+    );
+}
+
+#[test]
+fn combined_file_with_a_truncated_header() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("bigfile.big");
+    write_combined(&path, &[("a.txt", b"hello")]);
+
+    let combined = fs::read(&path).unwrap();
+    fs::write(&path, &combined[..20]).unwrap();
+    let err = BigFile::from_combined(path.clone()).err().unwrap();
+    assert_eq!(err.io_kind(), Some(io::ErrorKind::UnexpectedEof), "{err}");
+
+    // A header whose offsets run past the end of the file
+    let mut header = combined[..24].to_vec();
+    header[16..24].copy_from_slice(&1000u64.to_le_bytes());
+    fs::write(&path, header).unwrap();
+    let err = BigFile::from_combined(path).err().unwrap();
+    assert_eq!(err.io_kind(), Some(io::ErrorKind::InvalidData), "{err}");
+}
+
+#[test]
+fn crc32_of_entries() {
+    // Longer than the chunks entries are streamed in
+    let long = vec![b'a'; 100_000];
+    let (_dir, bigfile) = write_bigfile(&[
+        ("check.txt", b"123456789"),
+        ("long.bin", &long),
+        ("empty", b""),
+    ]);
+
+    assert_eq!(bigfile.entry_crc32("CHECK.TXT").unwrap(), 0xCBF43926);
+    assert_eq!(bigfile.entry_crc32("long.bin").unwrap(), 0x1BE2FA87);
+    assert_eq!(bigfile.entry_crc32("empty").unwrap(), 0);
+    assert!(matches!(
+        bigfile.entry_crc32("missing"),
+        Err(BigFileError::EntryNotFound(_))
+    ));
+
+    assert_eq!(
+        bigfile.checksums().unwrap(),
+        HashMap::from([
+            (PathBuf::from("./check.txt"), 0xCBF43926),
+            (PathBuf::from("./long.bin"), 0x1BE2FA87),
+            (PathBuf::from("./empty"), 0),
+        ])
+    );
+}
+
+#[test]
+fn writer_aligns_entries() {
+    let files: [(&str, &[u8]); 3] = [("a.txt", b"abc"), ("b.txt", b""), ("dir/c.txt", b"hello")];
+
+    let mut writer = BigFileWriter::new().with_alignment(2048);
+    for (path, data) in files {
+        writer.add_file(path.into(), data).unwrap();
+    }
+    writer
+        .add_file_from_reader("d.txt".into(), &b"streamed"[..], 8)
+        .unwrap();
+
+    let (mut bfn, mut bfdb, mut bfdata) = (Vec::new(), Vec::new(), Vec::new());
+    writer.write(&mut bfn, &mut bfdb, &mut bfdata).unwrap();
+
+    let records = Bfdb::read(&mut Cursor::new(&bfdb)).unwrap().records;
+    let offsets: Vec<_> = records.iter().map(|(_, entry)| entry.offset).collect();
+    assert_eq!(offsets, [0, 2048, 2048, 4096]);
+    assert_eq!(bfdata.len(), 4096 + 8);
+    assert!(bfdata[3..2048].iter().all(|&byte| byte == 0));
+
+    let bigfile = BigFile::new(
+        &mut Cursor::new(bfn),
+        &mut Cursor::new(bfdb),
+        &mut Cursor::new(bfdata),
+    )
+    .unwrap();
+    assert_contents(
+        &bigfile,
+        &[
+            ("a.txt", b"abc"),
+            ("b.txt", b""),
+            ("dir/c.txt", b"hello"),
+            ("d.txt", b"streamed"),
+        ],
+    );
+}
+
+#[test]
+fn in_memory_errors_name_the_part() {
+    let (dir, _) = write_bigfile(&[("a.txt", b"data")]);
+    let read = |ext: &str| fs::read(dir.path().join("bigfile").with_extension(ext)).unwrap();
+    let (bfn, bfdb, bfdata) = (read("bfn"), read("bfdb"), read("bfdata"));
+
+    let err = BigFile::new(
+        &mut Cursor::new(bfn[..bfn.len() - 2].to_vec()),
+        &mut Cursor::new(bfdb.clone()),
+        &mut Cursor::new(bfdata.clone()),
+    )
+    .err()
+    .unwrap();
+    assert!(
+        matches!(&err, BigFileError::Io { file: Some(file), .. } if file == Path::new("<bfn>"))
+    );
+    assert!(err.to_string().starts_with("<bfn> at offset"), "{err}");
+
+    let err = BigFile::new(
+        &mut Cursor::new(bfn),
+        &mut Cursor::new(bfdb[..10].to_vec()),
+        &mut Cursor::new(bfdata),
+    )
+    .err()
+    .unwrap();
+    assert!(err.to_string().starts_with("<bfdb> at offset"), "{err}");
+
+    let bigfile = BigFile::from_paths(
+        dir.path().join("bigfile.bfn"),
+        dir.path().join("bigfile.bfdb"),
+        DataSource::Dynamic(Box::new(Unreadable)),
+    )
+    .unwrap();
+    let err = bigfile.get("a.txt").unwrap_err();
+    assert!(err.to_string().starts_with("<bfdata> at offset"), "{err}");
+}
+
+// Claims to be a KiB long, but fails every read
+struct Unreadable;
+
+impl Read for Unreadable {
+    fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+        Err(io::Error::other("unreadable"))
+    }
+}
+
+impl Seek for Unreadable {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        match pos {
+            SeekFrom::Start(pos) => Ok(pos),
+            _ => Ok(1024),
+        }
+    }
+}
+
+#[test]
+fn read_each_hands_out_paths_in_order() {
+    let (dir, _) = write_bigfile(&[("a.txt", b""), ("dir/b.txt", b""), ("dir/sub/c.txt", b"")]);
+    let bfn_path = dir.path().join("bigfile.bfn");
+
+    let mut paths = Vec::new();
+    let root = Bfn::read_each(&mut File::open(&bfn_path).unwrap(), |path| {
+        paths.push(path.to_path_buf())
+    })
+    .unwrap();
+
+    let bfn = Bfn::read(&mut File::open(&bfn_path).unwrap()).unwrap();
+    assert_eq!(paths, bfn.files);
+    assert_eq!(root.dirs[0].dirs[0].files, ["c.txt"]);
+}
+
+// A bfn with a directory in a directory `depth` times, and no files
+fn nested_bfn(depth: usize) -> Vec<u8> {
+    let mut bfn = Vec::new();
+    for i in 0..=depth {
+        bfn.extend(1u32.to_le_bytes());
+        bfn.push(b'd');
+        bfn.extend(0u32.to_le_bytes());
+        bfn.extend(u32::from(i < depth).to_le_bytes());
+    }
+    bfn
+}
+
+#[test]
+fn deeply_nested_bfn_fails_instead_of_overflowing() {
+    let bfn = Bfn::read(&mut Cursor::new(nested_bfn(bigfile::DEFAULT_MAX_DEPTH))).unwrap();
+    assert_eq!(bfn.root.dirs.len(), 1);
+
+    for depth in [bigfile::DEFAULT_MAX_DEPTH + 1, 1_000_000] {
+        let err = Bfn::read(&mut Cursor::new(nested_bfn(depth)))
+            .err()
+            .unwrap();
+        assert_eq!(err.io_kind(), Some(io::ErrorKind::InvalidData));
+    }
+}
+
+#[test]
+fn options_max_depth() {
+    let (dir, _) = write_bigfile(&[("a/b/c.txt", b"data")]);
+    let bfn = dir.path().join("bigfile.bfn");
+    let open = |max_depth| {
+        BigFileOptions::new().max_depth(max_depth).open(
+            bfn.clone(),
+            bfn.with_extension("bfdb"),
+            bfn.with_extension("bfdata"),
+        )
+    };
+
+    assert_eq!(open(2).unwrap().get("a/b/c.txt").unwrap(), b"data");
+    assert!(open(1).is_err());
+}
+
+#[test]
+fn sniff_files_by_content() {
+    let (dir, _) = write_bigfile(&[("a.txt", b"hello"), ("dir/b.dds", b"DDS |")]);
+    let path = |ext: &str| dir.path().join("bigfile").with_extension(ext);
+
+    // Renamed so the extensions are no help
+    for (ext, renamed) in [("bfn", "1"), ("bfdb", "2"), ("bfdata", "3")] {
+        fs::rename(path(ext), path(renamed)).unwrap();
+    }
+
+    assert_eq!(bigfile::sniff_file(&path("1")).unwrap(), Part::Bfn);
+    assert_eq!(bigfile::sniff_file(&path("2")).unwrap(), Part::Bfdb);
+    assert_eq!(bigfile::sniff_file(&path("3")).unwrap(), Part::Bfdata);
+}
+
+#[test]
+fn options_index_cache() {
+    let (dir, _) = write_bigfile(&[("a.txt", b"hello"), ("dir/b.txt", b"world")]);
+    let bfn = dir.path().join("bigfile.bfn");
+    let cache = dir.path().join("bigfile.cache");
+    let open = || {
+        BigFileOptions::new().index_cache(&cache).open(
+            bfn.clone(),
+            bfn.with_extension("bfdb"),
+            bfn.with_extension("bfdata"),
+        )
+    };
+
+    assert_eq!(open().unwrap().get("dir/b.txt").unwrap(), b"world");
+    assert!(cache.exists());
+
+    // Same size and modification time, so the index comes from the cache without the bfn being read
+    let modified = fs::metadata(&bfn).unwrap().modified().unwrap();
+    let len = fs::metadata(&bfn).unwrap().len();
+    fs::write(&bfn, vec![0xFF; len as usize]).unwrap();
+    File::options()
+        .write(true)
+        .open(&bfn)
+        .unwrap()
+        .set_modified(modified)
+        .unwrap();
+
+    let bigfile = open().unwrap();
+    assert_eq!(bigfile.get("a.txt").unwrap(), b"hello");
+    assert_eq!(bigfile.get("dir/b.txt").unwrap(), b"world");
+
+    // Once the bfn looks changed, it's read again
+    File::options()
+        .write(true)
+        .open(&bfn)
+        .unwrap()
+        .set_modified(modified + std::time::Duration::from_secs(1))
+        .unwrap();
+    assert!(open().is_err());
+}
+
+#[test]
+fn writer_roundtrips_every_entry() {
+    let files: [(&str, &[u8]); 5] = [
+        ("readme.txt", b"hello"),
+        ("Textures/UI/Logo.dds", b"DDS logo"),
+        ("textures/ui/icons/a.dds", b"a"),
+        ("sounds/empty.ogg", b""),
+        ("deep/er/and/deeper/file.bin", &[0, 1, 2, 255]),
+    ];
+
+    let dir = TempDir::new().unwrap();
+    let bfn_path = dir.path().join("bigfile.bfn");
+    let mut writer = BigFileWriter::new();
+    for (path, data) in files {
+        writer.add_file(path.into(), data).unwrap();
+    }
+    writer
+        .write(
+            &mut File::create(&bfn_path).unwrap(),
+            &mut File::create(bfn_path.with_extension("bfdb")).unwrap(),
+            &mut File::create(bfn_path.with_extension("bfdata")).unwrap(),
+        )
+        .unwrap();
+
+    let bigfile = BigFile::open(bfn_path).unwrap();
+    assert_eq!(bigfile.len(), files.len());
+
+    let mut offset = 0;
+    for (path, data) in files {
+        let entry = bigfile.entries()[&Path::new(".").join(path)];
+        assert_eq!(entry.offset(), offset, "{path}");
+        assert_eq!(entry.size(), data.len() as u64, "{path}");
+        assert_eq!(bigfile.get(path).unwrap(), data, "{path}");
+        offset += data.len() as u64;
+    }
+}
+
+#[test]
+fn patch_leaves_shared_data_alone() {
+    let dir = TempDir::new().unwrap();
+    let bfn_path = write_files(dir.path(), &[("a.txt", b"aaaa"), ("b.txt", b"bbbb")]);
+    let bfdb_path = bfn_path.with_extension("bfdb");
+
+    // Both entries point at a.txt's data
+    let bigfile = BigFile::open(bfn_path.clone()).unwrap();
+    let a = bigfile.entries()[Path::new("./a.txt")];
+    let mut bfdb = Bfdb::read(&mut File::open(&bfdb_path).unwrap()).unwrap();
+    for (_, entry) in &mut bfdb.records {
+        entry.offset = a.offset();
+    }
+    bfdb.write(&mut File::create(&bfdb_path).unwrap()).unwrap();
+
+    let bigfile = BigFile::open(bfn_path.clone()).unwrap();
+    let replacements = HashMap::from([(PathBuf::from("a.txt"), b"NEW!".to_vec())]);
+    let mut bfdata = Vec::new();
+    let mut bfdb = Vec::new();
+    bigfile.patch(replacements, &mut bfdata, &mut bfdb).unwrap();
+
+    let patched = BigFile::new(
+        &mut Cursor::new(fs::read(&bfn_path).unwrap()),
+        &mut Cursor::new(bfdb),
+        &mut Cursor::new(bfdata),
+    )
+    .unwrap();
+    assert_eq!(patched.get("a.txt").unwrap(), b"NEW!");
+    assert_eq!(patched.get("b.txt").unwrap(), b"aaaa");
+}
+
+#[test]
+fn patch_appends_larger_data_and_keeps_the_bfdb_order() {
+    let dir = TempDir::new().unwrap();
+    let bfn_path = write_files(
+        dir.path(),
+        &[("a.txt", b"aaa"), ("b.txt", b"bbb"), ("c.txt", b"ccc")],
+    );
+    let bfdb_path = bfn_path.with_extension("bfdb");
+
+    // A record that no bfn path leads to, which has to survive the patch
+    let mut bfdb = Bfdb::read(&mut File::open(&bfdb_path).unwrap()).unwrap();
+    let orphan = bigfile::bfdb::Entry { offset: 1, size: 2 };
+    bfdb.records.insert(1, (0x1234, orphan));
+    bfdb.write(&mut File::create(&bfdb_path).unwrap()).unwrap();
+    let original = bfdb.records;
+
+    let bigfile = BigFile::open(bfn_path.clone()).unwrap();
+    let replacements = HashMap::from([(PathBuf::from("b.txt"), b"much longer".to_vec())]);
+    let mut bfdata = Vec::new();
+    let mut bfdb = Vec::new();
+    bigfile.patch(replacements, &mut bfdata, &mut bfdb).unwrap();
+
+    let records = Bfdb::read(&mut Cursor::new(&bfdb)).unwrap().records;
+    let hashes: Vec<_> = records.iter().map(|(hash, _)| *hash).collect();
+    let original_hashes: Vec<_> = original.iter().map(|(hash, _)| *hash).collect();
+    assert_eq!(hashes, original_hashes);
+    assert_eq!(records[1], (0x1234, orphan));
+    // Only the replaced entry moved, to the end of the old data
+    assert_eq!(records[0], original[0]);
+    assert_eq!(records[3], original[3]);
+    assert_eq!(
+        records[2].1,
+        bigfile::bfdb::Entry {
+            offset: 9,
+            size: 11
+        }
+    );
+
+    let patched = BigFile::new(
+        &mut Cursor::new(fs::read(&bfn_path).unwrap()),
+        &mut Cursor::new(bfdb),
+        &mut Cursor::new(bfdata),
+    )
+    .unwrap();
+    assert_eq!(patched.get("a.txt").unwrap(), b"aaa");
+    assert_eq!(patched.get("b.txt").unwrap(), b"much longer");
+    assert_eq!(patched.get("c.txt").unwrap(), b"ccc");
+    assert_eq!(patched.orphan_hashes(), [0x1234]);
+}
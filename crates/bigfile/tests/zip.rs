@@ -0,0 +1,34 @@
+mod common;
+
+use std::{fs::File, io::Read};
+
+use common::write_bigfile;
+use tempfile::TempDir;
+use zip::ZipArchive;
+
+#[test]
+fn extract_to_zip_keeps_directories_and_contents() {
+    let files: &[(&str, &[u8])] = &[
+        ("textures/logo.dds", b"logo"),
+        ("sounds/music/theme.ogg", &[7; 4096]),
+        ("empty.txt", b""),
+    ];
+    let (_dir, bigfile) = write_bigfile(files);
+    let out = TempDir::new().unwrap();
+    let zip_path = out.path().join("bigfile.zip");
+
+    bigfile
+        .extract_to_zip_with_level(zip_path.clone(), Some(9))
+        .unwrap();
+
+    let mut zip = ZipArchive::new(File::open(&zip_path).unwrap()).unwrap();
+    assert_eq!(zip.len(), files.len());
+    for (path, data) in files {
+        let mut contents = Vec::new();
+        zip.by_name(path)
+            .unwrap()
+            .read_to_end(&mut contents)
+            .unwrap();
+        assert_eq!(contents, *data, "{path}");
+    }
+}